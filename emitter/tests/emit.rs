@@ -0,0 +1,37 @@
+use std::path::Path;
+
+use emitter::KdlEmitter;
+use pulldown_kdl::{Event, Parser};
+
+/// The events of a document, without their ranges, which change as it's rewritten.
+fn events(text: &str) -> Vec<Event<'_>> {
+    Parser::new(text)
+        .map(|event| event.unwrap().0)
+        .filter(|event| !matches!(event, Event::Indentation(_)))
+        .collect()
+}
+
+/// Emits every fixture in this directory, checking the output against its `.txt` file and that it
+/// parses back into the same document.
+#[test]
+fn emits_fixtures() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let mut fixtures = 0;
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "kdl") {
+            continue;
+        }
+        let text = std::fs::read_to_string(&path).unwrap();
+        let mut output = Vec::new();
+        KdlEmitter::new(Parser::new(&text))
+            .emit(&mut output)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let expected = std::fs::read_to_string(path.with_extension("txt")).unwrap();
+        assert_eq!(output, expected, "{}", path.display());
+        assert_eq!(events(&output), events(&text), "{}", path.display());
+        fixtures += 1;
+    }
+    assert!(fixtures > 0);
+}
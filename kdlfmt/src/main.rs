@@ -0,0 +1,107 @@
+use std::{
+    ffi::OsString,
+    io::{Read, Write},
+    path::Path,
+};
+
+use miette::{IntoDiagnostic, WrapErr};
+use pulldown_kdl::{FormatOptions, Indent, Quotes, format};
+
+const USAGE: &str = "Usage: kdlfmt [--check | --write] [--tabs | --indent WIDTH] [--quote-values] \
+                     [--blank-lines COUNT] [FILE]...";
+
+enum Mode {
+    /// Prints the formatted documents.
+    Print,
+    /// Lists the files that aren't formatted, failing if there are any.
+    Check,
+    /// Formats the files in place.
+    Write,
+}
+
+struct Args {
+    files: Vec<OsString>,
+    mode: Mode,
+    options: FormatOptions,
+}
+
+impl Args {
+    fn cli() -> Result<Self, lexopt::Error> {
+        use lexopt::prelude::*;
+        let mut parser = lexopt::Parser::from_env();
+        let mut files = vec![];
+        let mut mode = Mode::Print;
+        let mut options = FormatOptions::default();
+        while let Some(a) = parser.next()? {
+            match a {
+                Value(val) => files.push(val),
+                Long("check") => mode = Mode::Check,
+                Long("write") => mode = Mode::Write,
+                Long("tabs") => options.style.indent = Indent::Tabs,
+                Long("indent") => options.style.indent = Indent::Spaces(parser.value()?.parse()?),
+                Long("quote-values") => options.style.quotes = Quotes::Values,
+                Long("blank-lines") => options.blank_lines = parser.value()?.parse()?,
+                Short('h') | Long("help") => {
+                    println!("{USAGE}");
+                    std::process::exit(0);
+                }
+                _ => return Err(a.unexpected()),
+            }
+        }
+        if files.is_empty() && matches!(mode, Mode::Write) {
+            return Err("--write needs files to format in place".into());
+        }
+        Ok(Self {
+            files,
+            mode,
+            options,
+        })
+    }
+}
+
+/// Formats a single document, returning whether it was already formatted.
+fn run(name: &Path, text: &str, args: &Args) -> miette::Result<bool> {
+    let formatted = format(text, &args.options)?;
+    let unchanged = formatted == text;
+    match args.mode {
+        Mode::Print => std::io::stdout()
+            .write_all(formatted.as_bytes())
+            .into_diagnostic()?,
+        Mode::Check if !unchanged => println!("{}", name.display()),
+        Mode::Check => (),
+        Mode::Write if !unchanged => std::fs::write(name, formatted).into_diagnostic()?,
+        Mode::Write => (),
+    }
+    Ok(unchanged)
+}
+
+fn main() -> miette::Result<()> {
+    let args = Args::cli().into_diagnostic()?;
+    let mut failed = false;
+    let mut unformatted = false;
+    if args.files.is_empty() {
+        let mut text = String::new();
+        std::io::stdin()
+            .read_to_string(&mut text)
+            .into_diagnostic()?;
+        unformatted = !run(Path::new("<stdin>"), &text, &args)?;
+    }
+    for file in &args.files {
+        let name = Path::new(file);
+        let result = std::fs::read_to_string(name)
+            .into_diagnostic()
+            .and_then(|text| run(name, &text, &args))
+            .wrap_err_with(|| format!("Failed to format {}", name.display()));
+        match result {
+            Ok(unchanged) => unformatted |= !unchanged,
+            Err(report) => {
+                eprintln!("{report:?}");
+                failed = true;
+            }
+        }
+    }
+    if failed || (unformatted && matches!(args.mode, Mode::Check)) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
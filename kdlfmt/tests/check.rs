@@ -0,0 +1,66 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Runs `kdlfmt` with `args`, giving it `input` through stdin.
+fn kdlfmt(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_kdlfmt"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn check_passes_formatted_documents() {
+    let output = kdlfmt(&["--check"], "node 1 {\n    child\n}\n");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(output.stdout.is_empty());
+}
+
+#[test]
+fn check_fails_unformatted_documents() {
+    let output = kdlfmt(&["--check"], "node   1 {child}");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "<stdin>\n");
+}
+
+#[test]
+fn check_fails_invalid_documents() {
+    let output = kdlfmt(&["--check"], "node (\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    assert!(!output.stderr.is_empty());
+}
+
+#[test]
+fn check_reports_chained_slashdashes_as_parse_errors() {
+    for input in ["_/-/-/-\r\u{e9}", "node /- /- 1 2\n"] {
+        let output = kdlfmt(&["--check"], input);
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("dangling_slashdash"), "{stderr}");
+        assert!(stderr.contains("nothing to comment out"), "{stderr}");
+    }
+}
+
+#[test]
+fn written_files_pass_check() {
+    let path = std::env::temp_dir().join(format!("kdlfmt-{}.kdl", std::process::id()));
+    std::fs::write(&path, "a;b   #true\n\n\n\nc").unwrap();
+    let path_arg = path.to_str().unwrap();
+    assert_eq!(kdlfmt(&["--check", path_arg], "").status.code(), Some(1));
+    assert_eq!(kdlfmt(&["--write", path_arg], "").status.code(), Some(0));
+    let formatted = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(kdlfmt(&["--check", path_arg], "").status.code(), Some(0));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(formatted, "a\nb #true\n\nc\n");
+}
@@ -0,0 +1,212 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+use futures_io::AsyncBufRead;
+
+use crate::prelude::*;
+use crate::{Diagnostics, Event, ParseError, ParserOptions, Ranged, ReadError, StreamParser};
+
+/// A [`Stream`] of the events in a document read from an [`AsyncBufRead`], such as a socket.
+///
+/// Like [`ReaderParser`](crate::ReaderParser), only the part of the document that wasn't parsed yet
+/// is kept in memory, so events are always owned.
+///
+/// Its items fail with a [`ReadError`] rather than a [`ParseError`], since the reader can fail
+/// too: [`ReadError::Io`] is returned when it does, and [`ReadError::Parse`] when the document is
+/// invalid.
+pub struct AsyncReaderParser<R> {
+    reader: R,
+    parser: StreamParser,
+    /// Whether the reader reached its end, so the parser has the whole document.
+    finished: bool,
+}
+
+impl<R: AsyncBufRead + Unpin> AsyncReaderParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParserOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParserOptions) -> Self {
+        Self {
+            reader,
+            parser: StreamParser::with_options(options),
+            finished: false,
+        }
+    }
+
+    /// Feeds the parser whatever the reader has buffered, finishing it once nothing is left.
+    fn poll_read(&mut self, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        let chunk = loop {
+            match Pin::new(&mut self.reader).poll_fill_buf(cx) {
+                Poll::Ready(Err(error)) if error.kind() == std::io::ErrorKind::Interrupted => {
+                    continue;
+                }
+                Poll::Ready(chunk) => break chunk?,
+                Poll::Pending => return Poll::Pending,
+            }
+        };
+        if chunk.is_empty() {
+            self.parser.finish();
+            self.finished = true;
+            return Poll::Ready(Ok(()));
+        }
+        let len = chunk.len();
+        self.parser.feed(chunk);
+        Pin::new(&mut self.reader).consume(len);
+        Poll::Ready(Ok(()))
+    }
+
+    /// The errors recovered from so far, if [`ParserOptions::recover`] is enabled.
+    pub fn errors(&self) -> &[ParseError<'static>] {
+        self.parser.errors()
+    }
+
+    /// Takes the errors recovered from so far, so they can be reported together.
+    pub fn into_diagnostics(self) -> Diagnostics<'static> {
+        self.parser.into_diagnostics()
+    }
+}
+
+impl<R: AsyncBufRead + Unpin> Stream for AsyncReaderParser<R> {
+    type Item = Result<Ranged<Event<'static>>, ReadError>;
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match this.parser.next_event() {
+                Err(error) if error.cause == NeedsMoreData && !this.finished => {
+                    match this.poll_read(cx) {
+                        Poll::Ready(Ok(())) => (),
+                        Poll::Ready(Err(error)) => return Poll::Ready(Some(Err(error.into()))),
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+                event => return Poll::Ready(event.map_err(ReadError::from).transpose()),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::io::ErrorKind;
+    use std::task::Waker;
+
+    use futures_io::AsyncRead;
+
+    use super::*;
+    use crate::Parser;
+
+    /// A reader returning each of its reads in turn, and then the end of the text. A read of
+    /// `None` isn't ready yet, and wakes the task to be polled again.
+    struct Reads {
+        reads: VecDeque<Option<Result<&'static str, ErrorKind>>>,
+        buffered: &'static [u8],
+    }
+
+    impl Reads {
+        fn new<const N: usize>(reads: [Option<Result<&'static str, ErrorKind>>; N]) -> Self {
+            Self {
+                reads: reads.into(),
+                buffered: &[],
+            }
+        }
+    }
+
+    impl AsyncRead for Reads {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            let chunk = match self.as_mut().poll_fill_buf(cx) {
+                Poll::Ready(chunk) => chunk?,
+                Poll::Pending => return Poll::Pending,
+            };
+            let len = chunk.len().min(buf.len());
+            buf[..len].copy_from_slice(&chunk[..len]);
+            self.consume(len);
+            Poll::Ready(Ok(len))
+        }
+    }
+
+    impl AsyncBufRead for Reads {
+        fn poll_fill_buf(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+        ) -> Poll<std::io::Result<&[u8]>> {
+            let this = self.get_mut();
+            if this.buffered.is_empty() {
+                match this.reads.pop_front() {
+                    Some(Some(Ok(text))) => this.buffered = text.as_bytes(),
+                    Some(Some(Err(kind))) => return Poll::Ready(Err(kind.into())),
+                    Some(None) => {
+                        cx.waker().wake_by_ref();
+                        return Poll::Pending;
+                    }
+                    None => (),
+                }
+            }
+            Poll::Ready(Ok(this.buffered))
+        }
+
+        fn consume(self: Pin<&mut Self>, amt: usize) {
+            let this = self.get_mut();
+            this.buffered = &this.buffered[amt..];
+        }
+    }
+
+    /// Polls `parser` until it has no more events, as an executor would.
+    fn collect(
+        mut parser: AsyncReaderParser<Reads>,
+    ) -> Vec<<AsyncReaderParser<Reads> as Stream>::Item> {
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut events = vec![];
+        loop {
+            match Pin::new(&mut parser).poll_next(&mut cx) {
+                Poll::Ready(Some(event)) => events.push(event),
+                Poll::Ready(None) => return events,
+                Poll::Pending => (),
+            }
+        }
+    }
+
+    #[test]
+    fn waits_for_pending_reads() {
+        let reads = Reads::new([
+            Some(Ok("node 1 {\n  ch")),
+            None,
+            Some(Err(ErrorKind::Interrupted)),
+            Some(Ok("ild")),
+            None,
+            Some(Ok("\n}\n")),
+        ]);
+        let events: Vec<_> = collect(AsyncReaderParser::new(reads))
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let expected: Vec<_> = Parser::new("node 1 {\n  child\n}\n")
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn reports_read_errors() {
+        let reads = Reads::new([
+            Some(Ok("node 1\nother")),
+            None,
+            Some(Err(ErrorKind::BrokenPipe)),
+        ]);
+        let mut events = collect(AsyncReaderParser::new(reads)).into_iter();
+        // The first node was read whole before the error, so it's still parsed.
+        for expected in Parser::new("node 1\n").take(4) {
+            assert_eq!(events.next().unwrap().unwrap(), expected.unwrap());
+        }
+        match events.next() {
+            Some(Err(ReadError::Io(error))) => assert_eq!(error.kind(), ErrorKind::BrokenPipe),
+            event => panic!("Expected an I/O error, got {event:?}"),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::borrow::Cow;
+
+use crate::error::Expected;
+use crate::prelude::*;
+use crate::string::is_newline;
+use crate::{Item, ParseResult, Text, item};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CommentKind {
+    /// A `// comment`, which goes until the end of the line.
+    Line,
+    /// A `/* comment */`, which may span multiple lines and be nested.
+    Block,
+}
+
+pub(crate) trait ParseComment<'text>: Buffer<'text> {
+    /// Looks for a comment, returning its text without the delimiters.
+    fn peek_comment(&self) -> ParseResult<Item<(Text<'text>, CommentKind)>> {
+        let mut acc = self.sub_accumulator();
+        if let Some(start) = acc.expect_sequence("//") {
+            acc.consume_range(&start);
+            let text = acc.remaining_text();
+            let len = text.find(is_newline).unwrap_or(text.len());
+            acc.advance_bytes(len);
+            return Ok(item(
+                (Cow::Borrowed(&text[..len]), CommentKind::Line),
+                acc.range(),
+            ));
+        }
+
+        let Some(start) = acc.expect_sequence("/*") else {
+            return Ok(None);
+        };
+        acc.consume_range(&start);
+        let text_start = acc.end();
+        let mut depth = 1;
+        while depth > 0 {
+            if let Some(open) = acc.expect_sequence("/*") {
+                acc.consume_range(&open);
+                depth += 1;
+            } else if let Some(close) = acc.expect_sequence("*/") {
+                acc.consume_range(&close);
+                depth -= 1;
+            } else if acc.consume_next_char().is_none() {
+                return Err(ParseErrorCause::expected(Expected::CommentEnd, None).at(acc.end()));
+            }
+        }
+        let text = &acc.text()[text_start..acc.end() - 2];
+        Ok(item((Cow::Borrowed(text), CommentKind::Block), acc.range()))
+    }
+}
+
+impl<'text, B> ParseComment<'text> for B where B: Buffer<'text> {}
@@ -0,0 +1,1053 @@
+use std::borrow::Cow;
+use std::ops::Range;
+
+use serde::de::value::{CowStrDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::{Event, FieldKind, KdlNodeEntry, KdlString, KdlValue, ParseError, Parser, Ranged};
+
+/// Deserializes an instance of `T` from a KDL document, as described in [`Deserializer`].
+pub fn from_str<'de, T: de::Deserialize<'de>>(text: &'de str) -> Result<T, DeError> {
+    T::deserialize(&mut Deserializer::new(text))
+}
+
+/// An error found while deserializing a document: either the document is invalid, or it doesn't
+/// match the type it's deserialized into.
+#[derive(Debug)]
+pub enum DeError {
+    Parse(ParseError<'static>),
+    Custom {
+        message: String,
+        /// Where the node or value that didn't match is, once known.
+        span: Option<Range<usize>>,
+        /// The document, so the error can be shown in context.
+        source: String,
+    },
+}
+
+impl DeError {
+    /// Marks where the error happened, unless it's already known.
+    fn at(self, at: Range<usize>) -> Self {
+        match self {
+            Self::Custom {
+                message,
+                span: None,
+                source,
+            } => Self::Custom {
+                message,
+                span: Some(at),
+                source,
+            },
+            error => error,
+        }
+    }
+}
+
+impl From<ParseError<'static>> for DeError {
+    fn from(error: ParseError<'static>) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl std::fmt::Display for DeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(error) => error.fmt(f),
+            Self::Custom { message, .. } => message.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for DeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            // The parse error is shown in place of this one, so its source comes next.
+            Self::Parse(error) => error.source(),
+            Self::Custom { .. } => None,
+        }
+    }
+}
+
+impl de::Error for DeError {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        Self::Custom {
+            message: message.to_string(),
+            span: None,
+            source: String::new(),
+        }
+    }
+}
+
+impl miette::Diagnostic for DeError {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        match self {
+            Self::Parse(error) => error.code(),
+            Self::Custom { .. } => Some(Box::new("pulldown_kdl::deserialize")),
+        }
+    }
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        match self {
+            Self::Parse(error) => error.labels(),
+            Self::Custom { span, .. } => {
+                let label = miette::LabeledSpan::underline(span.clone()?);
+                Some(Box::new(std::iter::once(label)))
+            }
+        }
+    }
+    fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        match self {
+            Self::Parse(error) => error.help(),
+            Self::Custom { .. } => None,
+        }
+    }
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        match self {
+            Self::Parse(error) => error.source_code(),
+            Self::Custom { span: None, .. } => None,
+            Self::Custom { source, .. } => Some(source),
+        }
+    }
+}
+
+/// A [`serde::Deserializer`] driven directly by the events of a [`Parser`], without building a
+/// tree of the document first.
+///
+/// A document is a map from node names to nodes, so its nodes are the fields of a struct. What a
+/// node turns into depends on what it holds:
+/// - A single argument is deserialized as its value, so `port 8080` fills a `port: u16` field.
+///   An empty node is a unit, and `#null` also stands for a missing [`Option`].
+/// - Several arguments are a sequence, as in `tags "a" "b"`.
+/// - Properties and children are the fields of a nested struct or map. When a key appears more than
+///   once, only its last property is kept. A node can't have both arguments and fields.
+/// - Without arguments or properties, the nodes in a children block are a sequence, whatever their
+///   names. By convention, they are called `-`.
+/// - Enum variants are either a string argument, for unit variants, or the name of the only node
+///   in the children block, which holds the variant's data.
+///
+/// The fields of a struct can also be read from the arguments of its node, in order, as chosen by
+/// [`DeserializerOptions::fields`].
+///
+/// Type annotations are ignored.
+pub struct Deserializer<'de> {
+    text: &'de str,
+    parser: Parser<'de>,
+    peeked: Option<Ranged<Event<'de>>>,
+    options: DeserializerOptions,
+}
+
+/// Changes how a [`Deserializer`] reads a document.
+#[derive(Clone, Copy, Debug)]
+pub struct DeserializerOptions {
+    /// Chooses which fields of a struct are read from the arguments of its node, given the name of
+    /// the struct (or of the enum, for struct variants) and the field's, as
+    /// [`SerializerOptions::fields`](crate::SerializerOptions::fields) does. The arguments fill the
+    /// [`FieldKind::Argument`] fields in order, and all other fields are read from either a
+    /// property or a child node.
+    pub fields: fn(Option<&'static str>, &str) -> FieldKind,
+}
+
+impl Default for DeserializerOptions {
+    fn default() -> Self {
+        Self {
+            fields: |_, _| FieldKind::Property,
+        }
+    }
+}
+
+impl<'de> Deserializer<'de> {
+    pub fn new(text: &'de str) -> Self {
+        Self::with_options(text, DeserializerOptions::default())
+    }
+
+    pub fn with_options(text: &'de str, options: DeserializerOptions) -> Self {
+        Self {
+            text,
+            parser: Parser::new(text),
+            peeked: None,
+            options,
+        }
+    }
+
+    /// Reads the next event, skipping indentation, which carries no data.
+    fn read(&mut self) -> Result<Option<Ranged<Event<'de>>>, DeError> {
+        loop {
+            match self.parser.next_event()? {
+                Some((Event::Indentation(_), _)) => (),
+                event => return Ok(event),
+            }
+        }
+    }
+
+    fn peek(&mut self) -> Result<Option<&Event<'de>>, DeError> {
+        if self.peeked.is_none() {
+            self.peeked = self.read()?;
+        }
+        Ok(self.peeked.as_ref().map(|(event, _)| event))
+    }
+
+    fn next(&mut self) -> Result<Ranged<Event<'de>>, DeError> {
+        match self.peeked.take() {
+            Some(event) => Ok(event),
+            None => match self.read()? {
+                Some(event) => Ok(event),
+                None => Err(self.unexpected(self.text.len()..self.text.len())),
+            },
+        }
+    }
+
+    /// The error for an event the document's structure doesn't allow, which the parser never
+    /// emits.
+    fn unexpected(&self, span: Range<usize>) -> DeError {
+        let error = de::Error::custom("The parser emitted an unexpected event");
+        self.locate(error, span)
+    }
+
+    /// Marks where the error happened, unless it's already known, along with the document.
+    fn locate(&self, error: DeError, span: Range<usize>) -> DeError {
+        match error.at(span) {
+            DeError::Custom {
+                message,
+                span,
+                source,
+            } if source.is_empty() => DeError::Custom {
+                message,
+                span,
+                source: self.text.to_owned(),
+            },
+            error => error,
+        }
+    }
+
+    /// Skips the rest of the open document, including its [`Event::EndDocument`].
+    fn skip_document(&mut self) -> Result<(), DeError> {
+        let mut depth = 0usize;
+        loop {
+            match self.next()? {
+                (Event::StartDocument, _) => depth += 1,
+                (Event::EndDocument, _) if depth == 0 => return Ok(()),
+                (Event::EndDocument, _) => depth -= 1,
+                _ => (),
+            }
+        }
+    }
+
+    /// Visits the nodes of the root document, as a map or as a sequence.
+    fn visit_document<V: Visitor<'de>>(
+        &mut self,
+        visitor: V,
+        seq: bool,
+    ) -> Result<V::Value, DeError> {
+        match self.next()? {
+            (Event::StartDocument, _) => (),
+            (_, span) => return Err(self.unexpected(span)),
+        }
+        let mut entries = Entries {
+            de: self,
+            properties: Vec::new().into_iter(),
+            value: None,
+            children: Children::Open,
+        };
+        let value = match seq {
+            true => visitor.visit_seq(&mut entries),
+            false => visitor.visit_map(&mut entries),
+        };
+        let value = value.map_err(|error| entries.de.locate(error, 0..entries.de.text.len()))?;
+        entries.finish()?;
+        Ok(value)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.visit_document(visitor, false)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.visit_document(visitor, true)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct map struct enum identifier ignored_any
+    }
+}
+
+/// How far into a children block (or the root document) the deserializer is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Children {
+    /// There's no children block.
+    None,
+    /// Its [`Event::StartDocument`] wasn't read yet.
+    Unread,
+    /// Its nodes are being read.
+    Open,
+    /// Its [`Event::EndDocument`] was read.
+    Done,
+}
+
+/// A node whose name was read, along with its entries, which are read as soon as the node is.
+struct Node<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    /// Where the node is, from its name up to its last entry.
+    span: Range<usize>,
+    arguments: Vec<Value<'de>>,
+    properties: Vec<(KdlString<'de>, Value<'de>)>,
+    children: Children,
+    /// The enum whose variant the node holds, if any, which chooses the kinds of its fields.
+    enum_name: Option<&'static str>,
+}
+
+impl<'a, 'de> Node<'a, 'de> {
+    fn read(de: &'a mut Deserializer<'de>, mut span: Range<usize>) -> Result<Self, DeError> {
+        let mut arguments = Vec::new();
+        let mut properties: Vec<(KdlString, Value)> = Vec::new();
+        while let Some(Event::NodeEntry(_)) = de.peek()? {
+            let (Event::NodeEntry(entry), entry_span) = de.next()? else {
+                unreachable!("The event was just peeked");
+            };
+            span.end = entry_span.end;
+            match entry {
+                KdlNodeEntry::Argument { value, .. } => arguments.push(Value {
+                    value,
+                    span: entry_span,
+                }),
+                KdlNodeEntry::Property { key, value, .. } => {
+                    properties.retain(|(k, _)| *k != key);
+                    let value = Value {
+                        value,
+                        span: entry_span,
+                    };
+                    properties.push((key, value));
+                }
+            }
+        }
+        let children = match de.peek()? {
+            Some(Event::StartDocument) => Children::Unread,
+            _ => Children::None,
+        };
+        Ok(Self {
+            de,
+            span,
+            arguments,
+            properties,
+            children,
+            enum_name: None,
+        })
+    }
+
+    /// Whether the node is just a `#null` argument.
+    fn is_null(&self) -> bool {
+        matches!(
+            &self.arguments[..],
+            [Value {
+                value: KdlValue::Null,
+                ..
+            }]
+        ) && self.properties.is_empty()
+            && self.children == Children::None
+    }
+
+    /// Whether the node only has arguments, if any.
+    fn only_arguments(&self) -> bool {
+        self.properties.is_empty() && self.children == Children::None
+    }
+
+    fn mixed_entries(&self) -> DeError {
+        de::Error::custom("A node can't have both arguments and properties or children")
+    }
+
+    /// Turns the arguments into the [`FieldKind::Argument`] fields of the struct called `name`, in
+    /// order, so they're visited before its properties and children.
+    fn arguments_as_fields(
+        &mut self,
+        name: Option<&'static str>,
+        fields: &'static [&'static str],
+    ) -> Result<(), DeError> {
+        let kind = self.de.options.fields;
+        let mut names = fields
+            .iter()
+            .filter(|field| kind(name, field) == FieldKind::Argument);
+        let mut properties = Vec::with_capacity(self.arguments.len() + self.properties.len());
+        for argument in self.arguments.drain(..) {
+            let Some(field) = names.next() else {
+                let error: DeError =
+                    de::Error::custom("The node has more arguments than its struct has fields for");
+                return Err(error.at(argument.span));
+            };
+            properties.push((KdlString::from_str(field), argument));
+        }
+        properties.append(&mut self.properties);
+        self.properties = properties;
+        Ok(())
+    }
+
+    /// The properties and children of the node, which can then be visited.
+    fn entries(&mut self) -> Entries<'_, 'de> {
+        Entries {
+            de: &mut *self.de,
+            properties: std::mem::take(&mut self.properties).into_iter(),
+            value: None,
+            children: std::mem::replace(&mut self.children, Children::None),
+        }
+    }
+
+    /// Visits the properties and children of the node as a map, or just the children as a
+    /// sequence.
+    fn visit_entries<V: Visitor<'de>>(
+        &mut self,
+        visitor: V,
+        seq: bool,
+    ) -> Result<V::Value, DeError> {
+        if !self.arguments.is_empty() {
+            return Err(self.mixed_entries());
+        }
+        let mut entries = self.entries();
+        let value = match seq {
+            true => visitor.visit_seq(&mut entries)?,
+            false => visitor.visit_map(&mut entries)?,
+        };
+        entries.finish()?;
+        Ok(value)
+    }
+
+    fn visit_any<V: Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value, DeError> {
+        if !self.only_arguments() {
+            return self.visit_entries(visitor, false);
+        }
+        match self.arguments.len() {
+            0 => visitor.visit_unit(),
+            1 => de::Deserializer::deserialize_any(self.arguments.remove(0), visitor),
+            _ => self.visit_arguments(visitor),
+        }
+    }
+
+    fn visit_arguments<V: Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value, DeError> {
+        let mut arguments = SeqDeserializer::new(std::mem::take(&mut self.arguments).into_iter());
+        let value = visitor.visit_seq(&mut arguments)?;
+        arguments.end()?;
+        Ok(value)
+    }
+
+    fn visit_seq<V: Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value, DeError> {
+        match self.arguments.is_empty() {
+            true if self.properties.is_empty() => self.visit_entries(visitor, true),
+            false if self.only_arguments() => self.visit_arguments(visitor),
+            _ => Err(self.mixed_entries()),
+        }
+    }
+
+    fn visit_enum<V: Visitor<'de>>(
+        &mut self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        if let [
+            Value {
+                value: KdlValue::String(_),
+                ..
+            },
+        ] = &self.arguments[..]
+            && self.only_arguments()
+        {
+            let variant = self.arguments.remove(0);
+            return de::Deserializer::deserialize_enum(variant, "", &[], visitor);
+        }
+        if !self.arguments.is_empty() || !self.properties.is_empty() {
+            return Err(de::Error::custom(
+                "An enum is either a string argument or the only node in a children block",
+            ));
+        }
+        let mut entries = self.entries();
+        let value = visitor.visit_enum(Variant {
+            entries: &mut entries,
+            name,
+        })?;
+        if let Some((_, span)) = entries.next_node()? {
+            let error: DeError =
+                de::Error::custom("An enum variant must be the only node in its block");
+            return Err(error.at(span));
+        }
+        entries.finish()?;
+        Ok(value)
+    }
+
+    /// Locates the error of a visit in the node, or else reads up to the end of the node.
+    fn end<T>(mut self, value: Result<T, DeError>) -> Result<T, DeError> {
+        let value = value.map_err(|error| self.de.locate(error, self.span.clone()))?;
+        self.entries().finish()?;
+        match self.de.next()? {
+            (Event::NodeEnd { .. }, _) => Ok(value),
+            (_, span) => Err(self.de.unexpected(span)),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Node<'_, 'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, DeError> {
+        let value = self.visit_any(visitor);
+        self.end(value)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        if self.is_null() {
+            let value = visitor.visit_none();
+            return self.end(value);
+        }
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, DeError> {
+        let value = self.visit_seq(visitor);
+        self.end(value)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(mut self, visitor: V) -> Result<V::Value, DeError> {
+        let value = self.visit_entries(visitor, false);
+        self.end(value)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let value = self
+            .arguments_as_fields(Some(name), fields)
+            .and_then(|()| self.visit_entries(visitor, false));
+        self.end(value)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        mut self,
+        name: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let value = self.visit_enum(name, visitor);
+        self.end(value)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let value = visitor.visit_unit();
+        self.end(value)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct identifier
+    }
+}
+
+/// A node holding the data of an enum variant.
+impl<'de> de::VariantAccess<'de> for Node<'_, 'de> {
+    type Error = DeError;
+
+    fn unit_variant(self) -> Result<(), DeError> {
+        de::Deserialize::deserialize(self)
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, DeError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _: usize, visitor: V) -> Result<V::Value, DeError> {
+        de::Deserializer::deserialize_seq(self, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        mut self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        let value = self
+            .arguments_as_fields(self.enum_name, fields)
+            .and_then(|()| self.visit_entries(visitor, false));
+        self.end(value)
+    }
+}
+
+/// The properties and then the child nodes of a node, or the nodes of the root document.
+struct Entries<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    properties: std::vec::IntoIter<(KdlString<'de>, Value<'de>)>,
+    /// The value for the last key visited.
+    value: Option<Pending<'de>>,
+    children: Children,
+}
+
+enum Pending<'de> {
+    Property(Value<'de>),
+    /// A node, which is read once its value is visited.
+    Node(Range<usize>),
+}
+
+impl<'de> Entries<'_, 'de> {
+    /// Reads the name of the next child node, along with where it is.
+    fn next_node(&mut self) -> Result<Option<Ranged<KdlString<'de>>>, DeError> {
+        match self.children {
+            Children::Unread => {
+                self.de.next()?;
+                self.children = Children::Open;
+            }
+            Children::Open => (),
+            Children::None | Children::Done => return Ok(None),
+        }
+        match self.de.next()? {
+            (Event::NodeName { name, .. }, span) => Ok(Some((name, span))),
+            (Event::EndDocument, _) => {
+                self.children = Children::Done;
+                Ok(None)
+            }
+            (_, span) => Err(self.de.unexpected(span)),
+        }
+    }
+
+    /// Skips the child nodes that weren't visited.
+    fn finish(mut self) -> Result<(), DeError> {
+        if self.children == Children::Unread {
+            self.de.next()?;
+            self.children = Children::Open;
+        }
+        if self.children == Children::Open {
+            self.de.skip_document()?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> de::MapAccess<'de> for Entries<'_, 'de> {
+    type Error = DeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DeError> {
+        let (key, span) = if let Some((key, value)) = self.properties.next() {
+            let span = value.span.clone();
+            self.value = Some(Pending::Property(value));
+            (key, span)
+        } else if let Some((name, span)) = self.next_node()? {
+            self.value = Some(Pending::Node(span.clone()));
+            (name, span)
+        } else {
+            return Ok(None);
+        };
+        let key = Value {
+            value: KdlValue::String(key),
+            span,
+        };
+        seed.deserialize(key).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, DeError> {
+        match self.value.take() {
+            Some(Pending::Property(value)) => seed.deserialize(value),
+            Some(Pending::Node(span)) => seed.deserialize(Node::read(self.de, span)?),
+            None => Err(de::Error::custom("A value was visited before its key")),
+        }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for Entries<'_, 'de> {
+    type Error = DeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DeError> {
+        match self.next_node()? {
+            Some((_, span)) => seed.deserialize(Node::read(self.de, span)?).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// The only node of a children block, whose name is a variant of the enum called `name`.
+struct Variant<'e, 'a, 'de> {
+    entries: &'e mut Entries<'a, 'de>,
+    name: &'static str,
+}
+
+impl<'e, 'de> de::EnumAccess<'de> for Variant<'e, '_, 'de> {
+    type Error = DeError;
+    type Variant = Node<'e, 'de>;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Self::Variant), DeError> {
+        let Some((name, span)) = self.entries.next_node()? else {
+            return Err(de::Error::custom("Expected a node naming the enum variant"));
+        };
+        let variant = Value {
+            value: KdlValue::String(name),
+            span: span.clone(),
+        };
+        let variant = seed.deserialize(variant)?;
+        let node = Node {
+            enum_name: Some(self.name),
+            ..Node::read(self.entries.de, span)?
+        };
+        Ok((variant, node))
+    }
+}
+
+/// The value of an argument or property, or a key.
+struct Value<'de> {
+    value: KdlValue<'de>,
+    span: Range<usize>,
+}
+
+impl<'de> de::Deserializer<'de> for Value<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        let value: Result<_, DeError> = match self.value {
+            KdlValue::String(KdlString {
+                string: Cow::Borrowed(string),
+            }) => visitor.visit_borrowed_str(string),
+            KdlValue::String(KdlString {
+                string: Cow::Owned(string),
+            }) => visitor.visit_string(string),
+            KdlValue::Num(number) => {
+                if let Some(number) = number.as_i64() {
+                    visitor.visit_i64(number)
+                } else if let Some(number) = number.as_u64() {
+                    visitor.visit_u64(number)
+                } else if let Some(number) = number.as_i128() {
+                    visitor.visit_i128(number)
+                } else {
+                    visitor.visit_f64(number.as_f64())
+                }
+            }
+            KdlValue::Bool(value) => visitor.visit_bool(value),
+            KdlValue::Null => visitor.visit_unit(),
+        };
+        value.map_err(|error| error.at(self.span))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.value {
+            KdlValue::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        match self.value {
+            KdlValue::String(variant) => {
+                let variant: CowStrDeserializer<DeError> = variant.string.into_deserializer();
+                visitor
+                    .visit_enum(variant)
+                    .map_err(|error| error.at(self.span))
+            }
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, DeError> for Value<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::ParseErrorCause;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Server<'a> {
+        host: &'a str,
+        port: u16,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config<'a> {
+        name: String,
+        ratio: f64,
+        debug: bool,
+        tags: Vec<String>,
+        #[serde(borrow)]
+        server: Server<'a>,
+        #[serde(borrow)]
+        backup: Server<'a>,
+        missing: Option<u8>,
+        nothing: Option<u8>,
+        present: Option<u8>,
+        unit: (),
+    }
+
+    #[test]
+    fn structs() {
+        let text = r#"
+            name "my \"app\""
+            ratio 0.5
+            debug #true
+            tags a "b c"
+            server host=localhost port=80 host=example.com
+            backup {
+                host "backup"
+                port 0x1F90
+            }
+            nothing #null
+            present 3
+            unit
+        "#;
+        let config: Config = from_str(text).unwrap();
+        assert_eq!(
+            config,
+            Config {
+                name: "my \"app\"".into(),
+                ratio: 0.5,
+                debug: true,
+                tags: vec!["a".into(), "b c".into()],
+                server: Server {
+                    host: "example.com",
+                    port: 80,
+                },
+                backup: Server {
+                    host: "backup",
+                    port: 8080,
+                },
+                missing: None,
+                nothing: None,
+                present: Some(3),
+                unit: (),
+            }
+        );
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Point(i32, i32),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[test]
+    fn enums() {
+        let text = r#"
+            - Empty
+            - { Circle 1.5; }
+            - { Point -1 2; }
+            - { Rect w=3 h=4; }
+            - { Rect { w 5; h 6; }; }
+        "#;
+        let shapes: Vec<Shape> = from_str(text).unwrap();
+        assert_eq!(
+            shapes,
+            [
+                Shape::Empty,
+                Shape::Circle(1.5),
+                Shape::Point(-1, 2),
+                Shape::Rect { w: 3, h: 4 },
+                Shape::Rect { w: 5, h: 6 },
+            ]
+        );
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Lists {
+        numbers: Vec<i64>,
+        empty: Vec<i64>,
+        nested: Vec<Vec<u8>>,
+        pairs: Vec<(String, bool)>,
+    }
+
+    #[test]
+    fn sequences() {
+        let text = r#"
+            numbers 1 -2 3
+            empty
+            nested {
+                - 1 2
+                - 3
+            }
+            pairs {
+                - a #true
+                - b #false
+            }
+        "#;
+        let lists: Lists = from_str(text).unwrap();
+        assert_eq!(
+            lists,
+            Lists {
+                numbers: vec![1, -2, 3],
+                empty: vec![],
+                nested: vec![vec![1, 2], vec![3]],
+                pairs: vec![("a".into(), true), ("b".into(), false)],
+            }
+        );
+
+        // The nodes of the root document are a sequence whatever their names.
+        let root: Vec<u8> = from_str("- 1\nanything 2\n").unwrap();
+        assert_eq!(root, [1, 2]);
+    }
+
+    #[test]
+    fn maps() {
+        // Properties come before children, and a repeated key keeps its last value.
+        let text = "limits a=1 b=5 b=2 {\n    c 3\n}\n";
+        let map: HashMap<String, HashMap<String, u32>> = from_str(text).unwrap();
+        let limits = HashMap::from([("a".into(), 1), ("b".into(), 2), ("c".into(), 3)]);
+        assert_eq!(map, HashMap::from([("limits".into(), limits)]));
+
+        let map: BTreeMap<&str, u64> = from_str("z 1\na 2\n").unwrap();
+        assert_eq!(map, BTreeMap::from([("a", 2), ("z", 1)]));
+    }
+
+    #[test]
+    fn skips_unknown_nodes() {
+        let text = "extra 1 {\n    deep { deeper; }\n}\nhost a\nport 1\nmore x=1\n";
+        let server: Server = from_str(text).unwrap();
+        assert_eq!(server, Server { host: "a", port: 1 });
+    }
+
+    /// The message of a [`DeError::Custom`], along with the text it points at.
+    fn custom_error<'a, T: Deserialize<'a> + std::fmt::Debug>(text: &'a str) -> (String, &'a str) {
+        match from_str::<T>(text).unwrap_err() {
+            DeError::Custom {
+                message,
+                span: Some(span),
+                source,
+            } => {
+                assert_eq!(source, text);
+                (message, &text[span])
+            }
+            error => panic!("Expected a located custom error, got {error:?}"),
+        }
+    }
+
+    #[test]
+    fn locates_errors() {
+        let (message, at) = custom_error::<Server>("host a\nport \"eighty\"\n");
+        assert!(message.contains("invalid type"), "{message}");
+        assert_eq!(at, "\"eighty\"");
+
+        let (message, at) = custom_error::<Server>("host a\nport 70000\n");
+        assert!(message.contains("70000"), "{message}");
+        assert_eq!(at, "70000");
+
+        let (message, at) = custom_error::<Config>("name a\nserver host=a port=x\n");
+        assert!(message.contains("invalid type"), "{message}");
+        assert_eq!(at, "port=x");
+
+        // A missing field is reported at the node that should have it.
+        let (message, at) = custom_error::<Config>("server host=a\n");
+        assert_eq!(message, "missing field `port`");
+        assert_eq!(at, "server host=a");
+
+        // Or at the whole document, for the root.
+        let text = "\nhost a\n";
+        let (message, at) = custom_error::<Server>(text);
+        assert_eq!(message, "missing field `port`");
+        assert_eq!(at, text);
+
+        let (message, at) = custom_error::<Lists>("numbers 1 k=2\n");
+        assert!(
+            message.contains("both arguments and properties"),
+            "{message}"
+        );
+        assert_eq!(at, "numbers 1 k=2");
+
+        let (message, at) = custom_error::<Vec<Shape>>("- { Hexagon; }\n");
+        assert!(message.contains("unknown variant `Hexagon`"), "{message}");
+        assert_eq!(at, "Hexagon");
+
+        let (message, at) = custom_error::<Vec<Shape>>("- { Empty; Circle 1; }\n");
+        assert!(message.contains("only node"), "{message}");
+        assert_eq!(at, "Circle");
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        match from_str::<Server>("host a\nport 1x\n") {
+            Err(DeError::Parse(error)) => {
+                assert_eq!(
+                    error.cause,
+                    ParseErrorCause::InvalidNumberCharacter { c: 'x' }
+                )
+            }
+            result => panic!("Expected a parse error, got {result:?}"),
+        }
+    }
+}
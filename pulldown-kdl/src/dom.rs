@@ -0,0 +1,322 @@
+//! A tree of the nodes in a document, for when the whole document is needed at once.
+//!
+//! The tree is built from the events of a [`Parser`], so its strings are borrowed from the
+//! source whenever they don't need unescaping. Call `into_owned` on a tree, which implements
+//! [`IntoOwned`](ownable::traits::IntoOwned), to get rid of the lifetime.
+
+use std::ops::Range;
+
+use ownable::IntoOwned;
+
+use crate::{Event, KdlNodeEntry, KdlString, KdlValue, ParseError, Parser, Ranged};
+
+/// A list of nodes, either a whole document or the children block of a node.
+#[derive(IntoOwned, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KdlDocument<'text> {
+    pub nodes: Vec<KdlNode<'text>>,
+    /// Where the document is, including the braces of a children block.
+    #[ownable(clone)]
+    pub span: Range<usize>,
+}
+
+/// A node along with its entries and children.
+#[derive(IntoOwned, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KdlNode<'text> {
+    /// The node's type annotation, such as the `date` in `(date)node`.
+    pub ty: Option<KdlString<'text>>,
+    pub name: KdlString<'text>,
+    pub arguments: Vec<KdlEntry<'text>>,
+    /// The properties along with their keys. When a key appears more than once, only its last
+    /// property is kept, as the KDL spec says.
+    pub properties: Vec<(KdlString<'text>, KdlEntry<'text>)>,
+    pub children: Option<KdlDocument<'text>>,
+    /// Where the node is, from its type annotation or name up to its last entry or children block.
+    #[ownable(clone)]
+    pub span: Range<usize>,
+}
+
+/// The value of an argument or property.
+#[derive(IntoOwned, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KdlEntry<'text> {
+    /// The value's type annotation, such as the `u8` in `(u8)10`.
+    pub ty: Option<KdlString<'text>>,
+    pub value: KdlValue<'text>,
+    /// Where the entry is, including the key of a property.
+    #[ownable(clone)]
+    pub span: Range<usize>,
+}
+
+impl<'text> KdlDocument<'text> {
+    /// Parses a whole document into a tree.
+    pub fn parse(text: &'text str) -> Result<Self, ParseError<'static>> {
+        Self::from_events(Parser::new(text))
+    }
+
+    /// Builds a tree from the events of a parser, returning the first error it finds.
+    ///
+    /// Comments and anything commented out with a slashdash are left out of the tree. The parser
+    /// may also recover from errors, in which case the nodes it skipped are left out too.
+    pub fn from_events<E>(
+        events: impl IntoIterator<Item = Result<Ranged<Event<'text>>, E>>,
+    ) -> Result<Self, E> {
+        let mut builder = Builder::default();
+        for event in events {
+            if let Some(document) = builder.push(event?) {
+                return Ok(document);
+            }
+        }
+        Ok(builder.documents.into_iter().next().unwrap_or_default())
+    }
+
+    /// The first node called `name`.
+    pub fn get(&self, name: &str) -> Option<&KdlNode<'text>> {
+        self.nodes.iter().find(|node| node.name.string == name)
+    }
+}
+
+impl<'text> KdlNode<'text> {
+    /// The value of the argument at `index`.
+    pub fn argument(&self, index: usize) -> Option<&KdlValue<'text>> {
+        self.arguments.get(index).map(|entry| &entry.value)
+    }
+
+    /// The value of the property called `key`.
+    pub fn property(&self, key: &str) -> Option<&KdlValue<'text>> {
+        self.properties
+            .iter()
+            .find(|(k, _)| k.string == key)
+            .map(|(_, entry)| &entry.value)
+    }
+
+    /// The nodes in the children block, which are none if the node has no children block.
+    pub fn children(&self) -> &[KdlNode<'text>] {
+        self.children
+            .as_ref()
+            .map_or(&[], |children| children.nodes.as_slice())
+    }
+
+    fn add_entry(&mut self, entry: KdlNodeEntry<'text>, span: Range<usize>) {
+        self.span.end = span.end;
+        match entry {
+            KdlNodeEntry::Argument { ty, value } => self.arguments.push(KdlEntry {
+                ty: ty.map(|(ty, _)| ty),
+                value,
+                span,
+            }),
+            KdlNodeEntry::Property { key, ty, value } => {
+                self.properties.retain(|(k, _)| *k != key);
+                let entry = KdlEntry {
+                    ty: ty.map(|(ty, _)| ty),
+                    value,
+                    span,
+                };
+                self.properties.push((key, entry));
+            }
+        }
+    }
+}
+
+/// The documents and nodes still open while building a tree. Every open document but the root one
+/// is the children block of the open node before it.
+#[derive(Default)]
+struct Builder<'text> {
+    documents: Vec<KdlDocument<'text>>,
+    nodes: Vec<KdlNode<'text>>,
+    /// How many slashdashes are open, whose events are skipped.
+    slashdashes: usize,
+}
+
+impl<'text> Builder<'text> {
+    /// Whether a node is open in the innermost document, which the next entry belongs to.
+    fn in_node(&self) -> bool {
+        self.nodes.len() == self.documents.len()
+    }
+
+    /// Adds an event to the tree, returning the whole tree once the root document ends.
+    fn push(&mut self, (event, span): Ranged<Event<'text>>) -> Option<KdlDocument<'text>> {
+        match event {
+            Event::SlashdashStart => self.slashdashes += 1,
+            Event::SlashdashEnd => self.slashdashes = self.slashdashes.saturating_sub(1),
+            _ if self.slashdashes > 0 => (),
+            Event::StartDocument => {
+                // The root document spans the whole text, even the blank space before its start.
+                let start = if self.documents.is_empty() {
+                    0
+                } else {
+                    span.start
+                };
+                self.documents.push(KdlDocument {
+                    nodes: Vec::new(),
+                    span: start..span.end,
+                });
+            }
+            Event::EndDocument => {
+                let mut document = self.documents.pop()?;
+                document.span.end = span.end;
+                if self.documents.is_empty() {
+                    return Some(document);
+                }
+                if self.in_node()
+                    && let Some(node) = self.nodes.last_mut()
+                {
+                    node.span.end = span.end;
+                    node.children = Some(document);
+                }
+            }
+            Event::NodeName { ty, name } => self.nodes.push(KdlNode {
+                ty: ty.map(|(ty, _)| ty),
+                name,
+                span,
+                ..Default::default()
+            }),
+            Event::NodeEntry(entry) => {
+                if self.in_node()
+                    && let Some(node) = self.nodes.last_mut()
+                {
+                    node.add_entry(entry, span);
+                }
+            }
+            Event::NodeEnd { .. } => {
+                if self.in_node()
+                    && let Some(node) = self.nodes.pop()
+                    && let Some(document) = self.documents.last_mut()
+                {
+                    document.nodes.push(node);
+                }
+            }
+            Event::Indentation(_) | Event::Comment { .. } | Event::Error => (),
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use super::*;
+    use crate::{KdlNumber, ParseErrorCause, ParserOptions};
+
+    fn string(text: &str) -> KdlValue<'_> {
+        KdlValue::String(KdlString::from_str(text))
+    }
+
+    fn number(text: &str) -> KdlValue<'_> {
+        KdlValue::Num(KdlNumber::parse(text).unwrap())
+    }
+
+    #[test]
+    fn builds_nested_nodes() {
+        let text = "(t)a 1 \"two\" k=#true {\n    b; c #null {\n        d\n    }\n}\ne\n";
+        let document = KdlDocument::parse(text).unwrap();
+        let names: Vec<_> = document.nodes.iter().map(|n| &*n.name.string).collect();
+        assert_eq!(names, ["a", "e"]);
+
+        let a = document.get("a").unwrap();
+        assert_eq!(a.ty, Some(KdlString::from_str("t")));
+        assert_eq!(a.argument(0), Some(&number("1")));
+        assert_eq!(a.argument(1), Some(&string("two")));
+        assert_eq!(a.argument(2), None);
+        assert_eq!(a.property("k"), Some(&KdlValue::Bool(true)));
+        assert_eq!(a.property("missing"), None);
+
+        let children: Vec<_> = a.children().iter().map(|n| &*n.name.string).collect();
+        assert_eq!(children, ["b", "c"]);
+        let c = &a.children()[1];
+        assert_eq!(c.argument(0), Some(&KdlValue::Null));
+        assert_eq!(c.children()[0].name.string, "d");
+        assert!(c.children()[0].children.is_none());
+        assert!(document.get("e").unwrap().children().is_empty());
+    }
+
+    #[test]
+    fn spans_cover_their_text() {
+        let text = "\n(t)node 1 k=(u8)2 {\n    child\n}\nother; last \"a\"\n";
+        let document = KdlDocument::parse(text).unwrap();
+        assert_eq!(document.span, 0..text.len());
+
+        let node = &document.nodes[0];
+        assert_eq!(
+            &text[node.span.clone()],
+            "(t)node 1 k=(u8)2 {\n    child\n}"
+        );
+        assert_eq!(&text[node.arguments[0].span.clone()], "1");
+        assert_eq!(&text[node.properties[0].1.span.clone()], "k=(u8)2");
+        let children = node.children.as_ref().unwrap();
+        assert_eq!(&text[children.span.clone()], "{\n    child\n}");
+        assert_eq!(&text[children.nodes[0].span.clone()], "child");
+
+        assert_eq!(&text[document.nodes[1].span.clone()], "other");
+        assert_eq!(&text[document.nodes[2].span.clone()], "last \"a\"");
+    }
+
+    #[test]
+    fn last_property_wins() {
+        let document = KdlDocument::parse("node a=1 b=2 a=3 1 a=4 c=5").unwrap();
+        let node = &document.nodes[0];
+        let keys: Vec<_> = node.properties.iter().map(|(k, _)| &*k.string).collect();
+        // A repeated key moves to where it was last given.
+        assert_eq!(keys, ["b", "a", "c"]);
+        assert_eq!(node.property("a"), Some(&number("4")));
+        assert_eq!(node.arguments.len(), 1);
+    }
+
+    #[test]
+    fn leaves_out_comments_and_slashdashes() {
+        let text =
+            "// comment\n/-gone 1\nnode /-2 3 /-k=4 /* inline */ /-{ hidden } {\n    /-child\n}\n";
+        let options = ParserOptions {
+            comments: true,
+            slashdash: true,
+            recover: false,
+        };
+        let document = KdlDocument::from_events(Parser::with_options(text, options)).unwrap();
+        assert_eq!(document, KdlDocument::parse(text).unwrap());
+        assert_eq!(document.nodes.len(), 1);
+        let node = &document.nodes[0];
+        assert_eq!(node.arguments.len(), 1);
+        assert_eq!(node.argument(0), Some(&number("3")));
+        assert!(node.properties.is_empty());
+        assert!(node.children().is_empty());
+        assert!(node.children.is_some());
+    }
+
+    #[test]
+    fn returns_the_first_error_or_recovers() {
+        let text = "a 1\nb (\nc 3\n";
+        let error = KdlDocument::parse(text).unwrap_err();
+        assert!(matches!(error.cause, ParseErrorCause::Expected { .. }));
+
+        let options = ParserOptions {
+            recover: true,
+            ..Default::default()
+        };
+        let document = KdlDocument::from_events(Parser::with_options(text, options)).unwrap();
+        assert_eq!(document.get("a").unwrap().argument(0), Some(&number("1")));
+        assert_eq!(document.get("c").unwrap().argument(0), Some(&number("3")));
+    }
+
+    #[test]
+    fn into_owned_outlives_the_text() {
+        const TEXT: &str = "node \"borrowed\" \"esc\\taped\" k=1 {\n    child\n}\n";
+        let text = TEXT.to_string();
+        let document = KdlDocument::parse(&text).unwrap();
+        let node = &document.nodes[0];
+        assert!(matches!(node.name.string, Cow::Borrowed(_)));
+        assert!(matches!(
+            node.argument(1),
+            Some(KdlValue::String(KdlString {
+                string: Cow::Owned(_)
+            }))
+        ));
+
+        let owned: KdlDocument<'static> = document.into_owned();
+        drop(text);
+        assert_eq!(owned, KdlDocument::parse(TEXT).unwrap());
+        assert_eq!(owned.nodes[0].argument(1), Some(&string("esc\taped")));
+        assert_eq!(owned.nodes[0].children()[0].name.string, "child");
+    }
+}
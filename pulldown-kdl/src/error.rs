@@ -1,55 +1,464 @@
 use std::borrow::Cow;
+use std::ops::Range;
 
-use miette::LabeledSpan;
+use miette::{LabeledSpan, MietteError, MietteSpanContents, SourceSpan, SpanContents};
 
+use crate::position::{LineIndex, Position};
+use crate::string::{BOM, is_newline};
 use crate::value::KdlValue;
 
+/// What the parser was looking for when it failed.
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Expected {
+    /// A `;` or a newline ending a node.
     LineEnd,
+    /// The `)` closing a type annotation.
+    AnnotationEnd,
+    /// The newline ending a line continuation.
+    Newline,
+    /// The name a node starts with.
+    NodeName,
+    /// Any other string, such as a type annotation.
+    String,
+    /// An argument, or the value of a property.
+    Value,
+    /// The quotes (and hashes, for raw strings) closing a string.
+    ClosingQuote { quote: String },
+    /// The `*/` closing a block comment.
+    CommentEnd,
+    /// The `}` closing the children block of `node`, whose `{` is at `opened_at`.
+    ClosingBrace { node: String, opened_at: usize },
+    /// A digit of a number, after a sign, a radix prefix, a `.` or an exponent.
+    Digit,
+}
+
+impl Expected {
+    fn code(&self) -> &'static str {
+        match self {
+            Self::LineEnd => "pulldown_kdl::expected_line_end",
+            Self::AnnotationEnd => "pulldown_kdl::expected_annotation_end",
+            Self::Newline => "pulldown_kdl::expected_newline",
+            Self::NodeName => "pulldown_kdl::expected_node_name",
+            Self::String => "pulldown_kdl::expected_string",
+            Self::Value => "pulldown_kdl::expected_value",
+            Self::ClosingQuote { .. } => "pulldown_kdl::expected_closing_quote",
+            Self::CommentEnd => "pulldown_kdl::expected_comment_end",
+            Self::ClosingBrace { .. } => "pulldown_kdl::expected_closing_brace",
+            Self::Digit => "pulldown_kdl::expected_digit",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::LineEnd => "expected ';' or a newline",
+            Self::AnnotationEnd => "expected ')'",
+            Self::Newline => "expected a newline",
+            Self::NodeName => "expected a node name",
+            Self::String => "expected a string",
+            Self::Value => "expected a value",
+            Self::ClosingQuote { .. } => "expected the closing quote",
+            Self::CommentEnd => "expected '*/'",
+            Self::ClosingBrace { .. } => "expected '}'",
+            Self::Digit => "expected a digit",
+        }
+    }
+}
+
+impl std::fmt::Display for Expected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::LineEnd => write!(f, "a line end (';' or newline)"),
+            Self::AnnotationEnd => write!(f, "')' to close the type annotation"),
+            Self::Newline => write!(f, "a newline after the line continuation ('\\')"),
+            Self::NodeName => write!(f, "a node name"),
+            Self::String => write!(f, "a string"),
+            Self::Value => write!(f, "a value"),
+            Self::ClosingQuote { quote } => write!(f, "'{quote}' to close the string"),
+            Self::CommentEnd => write!(f, "'*/' to close the comment"),
+            Self::ClosingBrace { node, .. } => write!(f, "'}}' to close the children of '{node}'"),
+            Self::Digit => write!(f, "a digit"),
+        }
+    }
+}
+
+/// What the parser found instead of what it [`Expected`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Found {
+    Char(char),
+    /// The end of the text.
+    Eof,
+}
+
+impl From<char> for Found {
+    fn from(c: char) -> Self {
+        Self::Char(c)
+    }
+}
+
+impl From<Option<char>> for Found {
+    fn from(c: Option<char>) -> Self {
+        c.map_or(Self::Eof, Self::Char)
+    }
+}
+
+impl std::fmt::Display for Found {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Char(c) if is_newline(*c) => write!(f, "a newline"),
+            Self::Char(c) => write!(f, "'{c}'"),
+            Self::Eof => write!(f, "the end of the text"),
+        }
+    }
 }
 
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum ParseErrorCause {
-    InvalidStringCharacter { c: char },
-    InvalidKey { value: KdlValue<'static> },
-    Expected(Expected),
+    InvalidNumberCharacter {
+        c: char,
+    },
+    InvalidEscape {
+        c: char,
+    },
+    /// An unicode escape that isn't in the `\u{XXXXXX}` form or that isn't a valid character.
+    InvalidUnicodeEscape,
+    /// A literal newline in a single-line string.
+    NewlineInString,
+    /// Something other than a newline right after the opening quotes of a multi-line string.
+    ContentOnOpeningLine,
+    /// Something other than whitespace right before the closing quotes of a multi-line string.
+    ContentOnClosingLine,
+    /// A line in a multi-line string that doesn't start with the indentation of its closing line.
+    InconsistentIndentation,
+    InvalidKey {
+        value: KdlValue<'static>,
+    },
+    UnknownKeyword {
+        keyword: String,
+    },
+    /// A keyword without its `#`, like `true` (as in KDL v1) or `inf`.
+    LegacyKeyword {
+        keyword: &'static str,
+    },
+    /// An identifier that starts like a number, such as `1a`, `-1a` or `.1a`.
+    NumberLikeIdentifier {
+        identifier: String,
+    },
+    /// A code point that can't appear literally anywhere in a document, such as most control
+    /// characters.
+    DisallowedCharacter {
+        c: char,
+    },
+    /// A bidirectional control character, which could make the source look different from how it is
+    /// parsed.
+    BidiControlCharacter {
+        c: char,
+    },
+    /// A byte order mark (U+FEFF) anywhere other than at the very start of the document.
+    MisplacedBom,
+    /// A property key with a type annotation, which only values may have.
+    AnnotatedKey,
+    /// A `/-` that isn't followed by a node, an entry or a children block.
+    DanglingSlashdash,
+    /// A `}` outside of any children block.
+    UnmatchedClosingBrace,
+    /// Bytes that aren't valid UTF-8, which every KDL document must be encoded in.
+    InvalidUtf8,
+    /// A byte order mark of an encoding other than UTF-8, such as UTF-16.
+    UnsupportedEncoding {
+        encoding: &'static str,
+    },
+    Expected {
+        expected: Expected,
+        found: Found,
+    },
     NeedsMoreData,
 }
 
+impl ParseErrorCause {
+    /// Locates the cause at `at` bytes into the text being parsed.
+    pub(crate) fn at(self, at: usize) -> Located {
+        Located { cause: self, at }
+    }
+
+    pub(crate) fn expected(expected: Expected, found: impl Into<Found>) -> Self {
+        Self::Expected {
+            expected,
+            found: found.into(),
+        }
+    }
+
+    /// A stable code identifying the kind of error, reported as the [`miette::Diagnostic::code`].
+    pub fn code(&self) -> &'static str {
+        use ParseErrorCause::*;
+        match self {
+            InvalidNumberCharacter { .. } => "pulldown_kdl::invalid_number_character",
+            InvalidEscape { .. } => "pulldown_kdl::invalid_escape",
+            InvalidUnicodeEscape => "pulldown_kdl::invalid_unicode_escape",
+            NewlineInString => "pulldown_kdl::newline_in_string",
+            ContentOnOpeningLine => "pulldown_kdl::content_on_opening_line",
+            ContentOnClosingLine => "pulldown_kdl::content_on_closing_line",
+            InconsistentIndentation => "pulldown_kdl::inconsistent_indentation",
+            InvalidKey { .. } => "pulldown_kdl::invalid_key",
+            UnknownKeyword { .. } => "pulldown_kdl::unknown_keyword",
+            LegacyKeyword { .. } => "pulldown_kdl::legacy_keyword",
+            NumberLikeIdentifier { .. } => "pulldown_kdl::number_like_identifier",
+            DisallowedCharacter { .. } => "pulldown_kdl::disallowed_character",
+            BidiControlCharacter { .. } => "pulldown_kdl::bidi_control_character",
+            MisplacedBom => "pulldown_kdl::misplaced_bom",
+            AnnotatedKey => "pulldown_kdl::annotated_key",
+            DanglingSlashdash => "pulldown_kdl::dangling_slashdash",
+            UnmatchedClosingBrace => "pulldown_kdl::unmatched_closing_brace",
+            InvalidUtf8 => "pulldown_kdl::invalid_utf8",
+            UnsupportedEncoding { .. } => "pulldown_kdl::unsupported_encoding",
+            ParseErrorCause::Expected { expected, .. } => expected.code(),
+            NeedsMoreData => "pulldown_kdl::needs_more_data",
+        }
+    }
+
+    /// A short description of what's wrong with the labeled text.
+    fn label(&self) -> &'static str {
+        use ParseErrorCause::*;
+        match self {
+            InvalidNumberCharacter { .. } => "invalid character",
+            InvalidEscape { .. } | InvalidUnicodeEscape => "invalid escape",
+            NewlineInString => "newline",
+            ContentOnOpeningLine => "expected a newline",
+            ContentOnClosingLine => "expected only whitespace before the closing quotes",
+            InconsistentIndentation => "inconsistent indentation",
+            InvalidKey { .. } => "not a string",
+            UnknownKeyword { .. } => "unknown keyword",
+            LegacyKeyword { .. } => "keyword without '#'",
+            NumberLikeIdentifier { .. } => "starts like a number",
+            DisallowedCharacter { .. } | BidiControlCharacter { .. } => "disallowed code point",
+            MisplacedBom => "byte order mark",
+            AnnotatedKey => "annotation on a key",
+            DanglingSlashdash => "nothing to comment out",
+            UnmatchedClosingBrace => "no children block to close",
+            InvalidUtf8 => "invalid UTF-8",
+            UnsupportedEncoding { .. } => "byte order mark",
+            ParseErrorCause::Expected { expected, .. } => expected.label(),
+            NeedsMoreData => "unexpected end",
+        }
+    }
+}
+
+/// A [`ParseErrorCause`] along with where it happened.
+///
+/// The location is relative to the text being parsed, which, for the `peek_*` family of functions,
+/// starts at the current position of the buffer they're called on.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Located {
+    pub(crate) cause: ParseErrorCause,
+    pub(crate) at: usize,
+}
+
+impl Located {
+    pub(crate) fn offset_by(self, amount: usize) -> Self {
+        Self {
+            at: self.at + amount,
+            ..self
+        }
+    }
+
+    /// Tells more specifically what was expected instead of a string, when that's what is missing.
+    pub(crate) fn expecting(self, expected: Expected) -> Self {
+        match self.cause {
+            ParseErrorCause::Expected {
+                expected: Expected::String,
+                found,
+            } => ParseErrorCause::Expected { expected, found }.at(self.at),
+            _ => self,
+        }
+    }
+}
+
+/// Where a piece of text starts in the whole document, for parsers that only keep part of it.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct Origin {
+    pub(crate) offset: usize,
+    /// How many lines come before the piece of text.
+    pub(crate) line: usize,
+}
+
+/// The text an error happened in, which may only be a piece of the whole document.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub(crate) struct Source<'text> {
+    pub(crate) text: Cow<'text, str>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) origin: Origin,
+}
+
+/// Spans are located in the whole document, so they're moved into the piece of text kept.
+impl miette::SourceCode for Source<'_> {
+    fn read_span<'a>(
+        &'a self,
+        span: &SourceSpan,
+        context_lines_before: usize,
+        context_lines_after: usize,
+    ) -> Result<Box<dyn SpanContents<'a> + 'a>, MietteError> {
+        let offset = span
+            .offset()
+            .checked_sub(self.origin.offset)
+            .ok_or(MietteError::OutOfBounds)?;
+        let contents = self.text.read_span(
+            &SourceSpan::new(offset.into(), span.len()),
+            context_lines_before,
+            context_lines_after,
+        )?;
+        Ok(Box::new(MietteSpanContents::new(
+            contents.data(),
+            SourceSpan::new(
+                (contents.span().offset() + self.origin.offset).into(),
+                contents.span().len(),
+            ),
+            contents.line() + self.origin.line,
+            contents.column(),
+            contents.line_count(),
+        )))
+    }
+}
+
+/// Errors without an explicit location are assumed to happen at the start of the text.
+impl From<ParseErrorCause> for Located {
+    fn from(cause: ParseErrorCause) -> Self {
+        cause.at(0)
+    }
+}
+
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ParseError<'text> {
     pub cause: ParseErrorCause,
     pub at: usize,
-    pub(crate) source: Cow<'text, str>,
+    pub(crate) source: Source<'text>,
 }
 
 impl<'text> ParseError<'text> {
     /// Converts the borrowed error into an owned one, eliminating the lifetime.
     pub fn into_owned(self) -> ParseError<'static> {
         ParseError {
-            source: Cow::Owned(self.source.into_owned()),
+            source: Source {
+                text: Cow::Owned(self.source.text.into_owned()),
+                origin: self.source.origin,
+            },
             ..self
         }
     }
+
+    /// The position of `offset`, if it's in the text the error kept.
+    fn position(&self, offset: usize) -> Option<Position> {
+        let offset = offset.checked_sub(self.source.origin.offset)?;
+        let position = LineIndex::new(&self.source.text).position(offset);
+        Some(Position {
+            line: position.line + self.source.origin.line,
+            ..position
+        })
+    }
+
+    /// Where the error is in the source. Covers the text that caused it when that's known, or the
+    /// character at [`ParseError::at`] otherwise.
+    pub fn span(&self) -> Range<usize> {
+        use ParseErrorCause::*;
+        let rest = self
+            .at
+            .checked_sub(self.source.origin.offset)
+            .and_then(|at| self.source.text.get(at..))
+            .unwrap_or_default();
+        let len = match &self.cause {
+            ParseErrorCause::Expected {
+                found: Found::Char(c),
+                ..
+            }
+            | InvalidNumberCharacter { c }
+            | DisallowedCharacter { c }
+            | BidiControlCharacter { c } => c.len_utf8(),
+            InvalidEscape { c } => '\\'.len_utf8() + c.len_utf8(),
+            UnknownKeyword { keyword } => keyword.len(),
+            LegacyKeyword { keyword } => keyword.len(),
+            NumberLikeIdentifier { identifier } => identifier.len(),
+            MisplacedBom => BOM.len_utf8(),
+            DanglingSlashdash => "/-".len(),
+            // Up to the closing `}` of the escape, unless the string ends first.
+            InvalidUnicodeEscape => match rest.find(|c| matches!(c, '}' | '"') || is_newline(c)) {
+                Some(end) if rest[end..].starts_with('}') => end + 1,
+                Some(end) => end,
+                None => rest.len(),
+            },
+            // Up to the end of the annotation or of the key, respectively.
+            AnnotatedKey => rest.find(')').map_or(rest.len(), |end| end + 1),
+            InvalidKey { .. } => rest.find(crate::string::is_equals).unwrap_or(rest.len()),
+            _ => rest.chars().next().map_or(0, char::len_utf8),
+        };
+        self.at..self.at + len
+    }
 }
 
 impl<'test> std::fmt::Display for ParseError<'test> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        use self::Expected as Ex;
         use ParseErrorCause::*;
         match &self.cause {
-            InvalidStringCharacter { c } => {
-                write!(f, "Got an invalid character '{c}' while parsing a string")
+            InvalidNumberCharacter { c } => {
+                write!(f, "Got an invalid character '{c}' while parsing a number")
             }
+            InvalidEscape { c } => write!(f, "'\\{c}' is not a valid escape sequence"),
+            InvalidUnicodeEscape => write!(f, "Invalid unicode escape sequence"),
+            NewlineInString => write!(f, "Single-line strings can't contain newlines"),
+            ContentOnOpeningLine => write!(
+                f,
+                "The opening quotes of a multi-line string must be followed by a newline"
+            ),
+            ContentOnClosingLine => write!(
+                f,
+                "The closing quotes of a multi-line string must be on their own line"
+            ),
+            InconsistentIndentation => write!(
+                f,
+                "Every line of a multi-line string must start with the indentation of its closing line"
+            ),
             InvalidKey { value } => write!(f, "Expected a valid string, but got a {value} instead"),
-            ParseErrorCause::Expected(Ex::LineEnd) => {
-                write!(f, "A line end (';' or newline) was expected")
+            UnknownKeyword { keyword } => write!(f, "Unknown keyword '{keyword}'"),
+            LegacyKeyword { keyword } => write!(
+                f,
+                "'{keyword}' is not a valid identifier, since KDL v2 keywords are prefixed with '#'"
+            ),
+            NumberLikeIdentifier { identifier } => write!(
+                f,
+                "'{identifier}' is not a valid identifier, since it starts like a number"
+            ),
+            DisallowedCharacter { c } => write!(
+                f,
+                "The code point U+{:04X} can't appear literally in a KDL document",
+                *c as u32
+            ),
+            BidiControlCharacter { c } => write!(
+                f,
+                "The bidirectional control character U+{:04X} can't appear literally in a KDL document",
+                *c as u32
+            ),
+            MisplacedBom => write!(
+                f,
+                "A byte order mark (U+FEFF) is only allowed at the start of the document"
+            ),
+            AnnotatedKey => write!(f, "Property keys can't have type annotations"),
+            DanglingSlashdash => write!(f, "Slashdash ('/-') has nothing to comment out"),
+            UnmatchedClosingBrace => write!(f, "Found a '}}' without a children block to close"),
+            InvalidUtf8 => write!(f, "The text is not valid UTF-8"),
+            UnsupportedEncoding { encoding } => write!(f, "The text is encoded in {encoding}"),
+            ParseErrorCause::Expected {
+                expected: expected @ self::Expected::ClosingBrace { opened_at, .. },
+                found,
+            } if let Some(opened) = self.position(*opened_at) => {
+                write!(f, "Expected {expected} opened at {opened}, found {found}")
             }
-            NeedsMoreData => write!(f, "The source ended abrubtly"),
+            ParseErrorCause::Expected { expected, found } => {
+                write!(f, "Expected {expected}, found {found}")
+            }
+            NeedsMoreData => write!(f, "The source ended abruptly"),
         }
     }
 }
@@ -57,19 +466,178 @@ impl<'test> std::fmt::Display for ParseError<'test> {
 impl std::error::Error for ParseError<'_> {}
 
 impl<'text> miette::Diagnostic for ParseError<'text> {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        Some(Box::new(self.cause.code()))
+    }
     fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
-        Some(Box::new(
-            vec![LabeledSpan::new(Some("here".into()), self.at, 1)].into_iter(),
-        ))
+        let span = self.span();
+        let label = LabeledSpan::new(Some(self.cause.label().into()), span.start, span.len());
+        // Unclosed children blocks also point at where they were opened, if that's still known.
+        let opening = match &self.cause {
+            ParseErrorCause::Expected {
+                expected: Expected::ClosingBrace { opened_at, .. },
+                ..
+            } if *opened_at >= self.source.origin.offset => Some(LabeledSpan::new(
+                Some("children block opened here".into()),
+                *opened_at,
+                1,
+            )),
+            _ => None,
+        };
+        Some(Box::new(std::iter::once(label).chain(opening)))
     }
     fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
-        if let ParseErrorCause::Expected(Expected::LineEnd) = self.cause {
-            Some(Box::new("Insert a line break or a semicolon"))
-        } else {
-            None
+        match &self.cause {
+            ParseErrorCause::Expected { expected, .. } => match expected {
+                Expected::LineEnd => Some(Box::new("Insert a line break or a semicolon")),
+                Expected::AnnotationEnd => Some(Box::new("Close the type annotation with ')'")),
+                Expected::Newline => Some(Box::new(
+                    "Only whitespace and comments may follow a '\\' on its line",
+                )),
+                Expected::NodeName | Expected::String | Expected::Value => Some(Box::new(
+                    "Quote the string (\"...\") if it should contain this character",
+                )),
+                Expected::ClosingQuote { quote } => Some(Box::new(format!(
+                    "Close the string with {quote}, or escape the quotes inside it"
+                ))),
+                Expected::CommentEnd => Some(Box::new(
+                    "Block comments can be nested, so every '/*' needs its own '*/'",
+                )),
+                Expected::ClosingBrace { node, .. } => Some(Box::new(format!(
+                    "Close the children block of '{node}' with '}}'"
+                ))),
+                Expected::Digit => Some(Box::new(
+                    "Numbers must have a digit after their sign, radix prefix, '.' and exponent",
+                )),
+            },
+            ParseErrorCause::LegacyKeyword { keyword } => Some(Box::new(format!(
+                "Write '#{keyword}' instead, or quote it (\"{keyword}\") if a string was intended"
+            ))),
+            ParseErrorCause::InvalidEscape { .. } => Some(Box::new(
+                "Valid escapes are \\n, \\r, \\t, \\\\, \\\", \\b, \\f, \\s, \\u{XXXX} and \\ followed by whitespace",
+            )),
+            ParseErrorCause::InvalidUnicodeEscape => Some(Box::new(
+                "Unicode escapes must have between 1 and 6 hexadecimal digits, as in \\u{1F600}, and must not be surrogates",
+            )),
+            ParseErrorCause::NewlineInString => Some(Box::new(
+                "Use a multi-line string (\"\"\") or escape the newline with \\n",
+            )),
+            ParseErrorCause::InconsistentIndentation => Some(Box::new(
+                "Indent this line with the same whitespace as the line of the closing quotes",
+            )),
+            ParseErrorCause::UnknownKeyword { .. } => Some(Box::new(
+                "Valid keywords are #true, #false, #null, #inf, #-inf and #nan",
+            )),
+            ParseErrorCause::NumberLikeIdentifier { identifier } => Some(Box::new(format!(
+                "Quote it (\"{identifier}\") if a string was intended"
+            ))),
+            ParseErrorCause::DisallowedCharacter { .. }
+            | ParseErrorCause::BidiControlCharacter { .. }
+            | ParseErrorCause::MisplacedBom => Some(Box::new(
+                "Remove it, or use a \\u{XXXX} escape inside a quoted string",
+            )),
+            ParseErrorCause::AnnotatedKey => Some(Box::new(
+                "Annotate the property's value instead, as in key=(type)value",
+            )),
+            ParseErrorCause::DanglingSlashdash => Some(Box::new(
+                "'/-' comments out the node, entry or children block right after it",
+            )),
+            ParseErrorCause::InvalidUtf8 | ParseErrorCause::UnsupportedEncoding { .. } => {
+                Some(Box::new("KDL documents must be encoded in UTF-8"))
+            }
+            ParseErrorCause::UnmatchedClosingBrace => Some(Box::new(
+                "Remove the '}', or add the '{' it was meant to pair with",
+            )),
+            ParseErrorCause::InvalidNumberCharacter { .. } => Some(Box::new(
+                "Numbers are made of digits, '_', an optional fraction and exponent; quote it if a string was intended",
+            )),
+            ParseErrorCause::ContentOnOpeningLine => Some(Box::new(
+                "Start the content of the string on the line after the opening quotes",
+            )),
+            ParseErrorCause::ContentOnClosingLine => {
+                Some(Box::new("Move the closing quotes to a line of their own"))
+            }
+            ParseErrorCause::InvalidKey { .. } => Some(Box::new(
+                "Property keys must be strings, so quote it if it should be one",
+            )),
+            ParseErrorCause::NeedsMoreData => Some(Box::new(
+                "Look for an unclosed string, comment, type annotation or children block",
+            )),
         }
     }
     fn source_code(&self) -> Option<&dyn miette::SourceCode> {
         Some(&self.source)
     }
 }
+
+/// Every error found while parsing a document, reported together.
+///
+/// Errors are usually collected by parsing with [`ParserOptions::recover`] enabled and then
+/// calling [`Parser::into_diagnostics`].
+///
+/// [`ParserOptions::recover`]: crate::ParserOptions::recover
+/// [`Parser::into_diagnostics`]: crate::Parser::into_diagnostics
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Diagnostics<'text> {
+    errors: Vec<ParseError<'text>>,
+}
+
+impl<'text> Diagnostics<'text> {
+    pub fn errors(&self) -> &[ParseError<'text>] {
+        &self.errors
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Converts the borrowed errors into owned ones, eliminating the lifetime.
+    pub fn into_owned(self) -> Diagnostics<'static> {
+        self.errors
+            .into_iter()
+            .map(ParseError::into_owned)
+            .collect()
+    }
+}
+
+impl<'text> FromIterator<ParseError<'text>> for Diagnostics<'text> {
+    fn from_iter<T: IntoIterator<Item = ParseError<'text>>>(iter: T) -> Self {
+        Self {
+            errors: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<'text> IntoIterator for Diagnostics<'text> {
+    type Item = ParseError<'text>;
+    type IntoIter = std::vec::IntoIter<ParseError<'text>>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+impl std::fmt::Display for Diagnostics<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.errors.len() {
+            1 => write!(f, "Found 1 error while parsing"),
+            n => write!(f, "Found {n} errors while parsing"),
+        }
+    }
+}
+
+impl std::error::Error for Diagnostics<'_> {}
+
+impl miette::Diagnostic for Diagnostics<'_> {
+    fn related<'a>(&'a self) -> Option<Box<dyn Iterator<Item = &'a dyn miette::Diagnostic> + 'a>> {
+        Some(Box::new(
+            self.errors
+                .iter()
+                .map(|error| error as &dyn miette::Diagnostic),
+        ))
+    }
+}
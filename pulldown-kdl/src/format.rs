@@ -0,0 +1,112 @@
+use crate::string::is_newline;
+use crate::{Event, ParseError, Parser, ParserOptions, WriteError, Writer, WriterOptions};
+
+/// Changes how [`format()`] lays out a document.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FormatOptions {
+    /// How nodes, entries and strings are written.
+    pub style: WriterOptions,
+    /// How many blank lines in a row are kept between nodes, with any more left out.
+    pub blank_lines: usize,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            style: WriterOptions::default(),
+            blank_lines: 1,
+        }
+    }
+}
+
+/// An error found while formatting a document: either the document is invalid, or it has
+/// something that can't be written back, such as a node with two children blocks.
+#[derive(Debug)]
+pub enum FormatError {
+    Parse(ParseError<'static>),
+    Write(WriteError),
+}
+
+impl From<ParseError<'static>> for FormatError {
+    fn from(error: ParseError<'static>) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl From<WriteError> for FormatError {
+    fn from(error: WriteError) -> Self {
+        Self::Write(error)
+    }
+}
+
+impl std::fmt::Display for FormatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(error) => error.fmt(f),
+            Self::Write(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            // The inner error is shown in place of this one, so its source comes next.
+            Self::Parse(error) => error.source(),
+            Self::Write(error) => error.source(),
+        }
+    }
+}
+
+impl miette::Diagnostic for FormatError {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        match self {
+            Self::Parse(error) => error.code(),
+            Self::Write(_) => Some(Box::new("pulldown_kdl::format")),
+        }
+    }
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        match self {
+            Self::Parse(error) => error.labels(),
+            Self::Write(_) => None,
+        }
+    }
+    fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        match self {
+            Self::Parse(error) => error.help(),
+            Self::Write(_) => None,
+        }
+    }
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        match self {
+            Self::Parse(error) => error.source_code(),
+            Self::Write(_) => None,
+        }
+    }
+}
+
+/// Formats a document in a canonical layout, as a [`Writer`] writes it.
+///
+/// Comments and slashdashes are kept, and so are line breaks between a block comment and what
+/// comes after it, along with some blank lines between nodes, as [`FormatOptions::blank_lines`]
+/// says. Everything else is laid out anew, so any two documents with the same nodes, comments and
+/// blank lines are formatted the same.
+pub fn format(text: &str, options: &FormatOptions) -> Result<String, FormatError> {
+    let parser_options = ParserOptions {
+        comments: true,
+        slashdash: true,
+        recover: false,
+    };
+    let mut writer = Writer::with_options(String::new(), options.style);
+    for event in Parser::with_options(text, parser_options) {
+        let (event, range) = event?;
+        if let Event::Indentation(_) = event {
+            // A `\r\n` is a single newline.
+            let space = &text[range];
+            let newlines = space.matches(is_newline).count() - space.matches("\r\n").count();
+            writer.break_line(newlines, options.blank_lines);
+        }
+        writer.write_event(event)?;
+    }
+    Ok(writer.finish()?)
+}
@@ -1,27 +1,58 @@
 #![doc = include_str!("../../README.md")]
-#![feature(let_chains)]
 use std::borrow::Cow;
 use std::ops::Range;
 use std::str;
 
+#[cfg(feature = "async")]
+pub(crate) mod async_reader;
+pub(crate) mod comment;
+#[cfg(feature = "serde")]
+pub(crate) mod de;
+#[cfg(feature = "dom")]
+pub mod dom;
 pub(crate) mod error;
+pub(crate) mod format;
+pub(crate) mod number;
 pub(crate) mod parser;
+pub(crate) mod position;
 pub(crate) mod prelude;
+pub(crate) mod reader;
+#[cfg(feature = "serde")]
+pub(crate) mod ser;
+pub(crate) mod stream;
 pub(crate) mod string;
 pub(crate) mod utils;
 pub(crate) mod value;
+pub(crate) mod writer;
 
-pub use error::{ParseError, ParseErrorCause};
+#[cfg(feature = "async")]
+pub use async_reader::AsyncReaderParser;
+pub use comment::CommentKind;
+#[cfg(feature = "serde")]
+pub use de::{DeError, Deserializer, DeserializerOptions, from_str};
+pub use error::{Diagnostics, Expected, Found, ParseError, ParseErrorCause};
+pub use format::{FormatError, FormatOptions, format};
+pub use number::KdlNumber;
+pub use position::{LineIndex, Position, PositionedParser, Span};
 
+use comment::ParseComment;
 use parser::Parse;
 use prelude::*;
+pub use reader::{ReadError, ReaderParser};
+#[cfg(feature = "serde")]
+pub use ser::{
+    FieldKind, FieldsSerializer, SeqSerializer, SerError, Serializer, SerializerOptions, to_string,
+    to_writer,
+};
+pub use stream::StreamParser;
 pub use string::KdlString;
-use string::{is_equals, ParseString};
-use utils::OptionExt;
+use string::{ParseString, is_equals};
 pub use value::KdlValue;
+pub use writer::{Indent, IoWriter, Output, Quotes, WriteError, Writer, WriterOptions};
 
 /// Ad-hoc tracing/debug facilities
 /// If the `debug` feature is not enabled, does nothing
+#[allow(unused_macros)]
 macro_rules! tdbg {
     ($expr:expr) => {{
         if cfg!(feature = "debug") {
@@ -40,7 +71,9 @@ macro_rules! tprintln {
     }};
 }
 
+#[allow(unused_imports)]
 pub(crate) use tdbg;
+#[allow(unused_imports)]
 pub(crate) use tprintln;
 
 /// Represents the current parser state.
@@ -53,30 +86,141 @@ enum State {
     /// After a node name is found, it is emitted and the parser is now looking for node entries,
     /// which are properties and/or arguments.
     NodeEntries,
-    /// After a document ends and it is not the root document, we must also emit a [`Event::NodeEnd`] event.
+    /// After a children document ends, the node it belongs to must also end with a [`Event::NodeEnd`].
     DocumentEnd,
     /// Means the parser managed to parse a document to the end and further attempts to get more tokens will result in `None`.
     Final,
 }
+
+/// What a slashdash (`/-`) comments out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Slashdash {
+    /// A whole node, up to and including its [`Event::NodeEnd`].
+    Node,
+    /// A single argument or property.
+    Entry,
+    /// A children block, up to and including its [`Event::EndDocument`].
+    Children,
+}
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum KdlNodeEntry<'text> {
-    Argument(KdlValue<'text>),
+    Argument {
+        /// The value's type annotation, such as the `(u8)` in `(u8)10`, and where it is.
+        ty: Option<Ranged<KdlString<'text>>>,
+        value: KdlValue<'text>,
+    },
     Property {
         key: KdlString<'text>,
+        /// The value's type annotation, such as the `(u8)` in `key=(u8)10`, and where it is.
+        ty: Option<Ranged<KdlString<'text>>>,
         value: KdlValue<'text>,
     },
 }
 
+impl<'text> KdlNodeEntry<'text> {
+    /// The entry's value, whether it is an argument or a property.
+    pub fn value(&self) -> &KdlValue<'text> {
+        match self {
+            Self::Argument { value, .. } | Self::Property { value, .. } => value,
+        }
+    }
+
+    /// The type annotation of the entry's value, if any.
+    pub fn ty(&self) -> Option<&Ranged<KdlString<'text>>> {
+        match self {
+            Self::Argument { ty, .. } | Self::Property { ty, .. } => ty.as_ref(),
+        }
+    }
+
+    /// Converts the borrowed entry into an owned one, eliminating the lifetime.
+    pub fn into_owned(self) -> KdlNodeEntry<'static> {
+        match self {
+            Self::Argument { ty, value } => KdlNodeEntry::Argument {
+                ty: owned_ty(ty),
+                value: value.into_owned(),
+            },
+            Self::Property { key, ty, value } => KdlNodeEntry::Property {
+                key: key.into_owned(),
+                ty: owned_ty(ty),
+                value: value.into_owned(),
+            },
+        }
+    }
+}
+
+fn owned_ty(ty: Option<Ranged<KdlString>>) -> Option<Ranged<KdlString<'static>>> {
+    ty.map(|(ty, range)| (ty.into_owned(), range))
+}
+
 #[derive(Clone, Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Event<'text> {
     StartDocument,
     EndDocument,
     Indentation(usize),
-    NodeName(KdlString<'text>),
+    NodeName {
+        /// The node's type annotation, such as the `(date)` in `(date)node`, and where it is.
+        ty: Option<Ranged<KdlString<'text>>>,
+        name: KdlString<'text>,
+    },
     NodeEntry(KdlNodeEntry<'text>),
-    NodeEnd { inline: bool },
+    NodeEnd {
+        inline: bool,
+    },
+    /// Only emitted if [`ParserOptions::comments`] is enabled, otherwise comments are skipped.
+    Comment {
+        text: Text<'text>,
+        kind: CommentKind,
+    },
+    /// A `/-`. Every event up to the matching [`Event::SlashdashEnd`] belongs to the node, entry or
+    /// children block it comments out.
+    ///
+    /// Only emitted if [`ParserOptions::slashdash`] is enabled, otherwise commented out items are
+    /// skipped entirely.
+    SlashdashStart,
+    /// Ends the item commented out by the last unmatched [`Event::SlashdashStart`].
+    SlashdashEnd,
+    /// Marks where the parser recovered from an error, spanning the text it skipped to do so. The
+    /// error itself is recorded in [`Parser::errors`].
+    ///
+    /// Only emitted if [`ParserOptions::recover`] is enabled, otherwise errors are returned instead.
+    Error,
+}
+
+impl<'text> Event<'text> {
+    /// The type annotation of the node name or entry in this event, if any.
+    fn ty_mut(&mut self) -> Option<&mut Ranged<KdlString<'text>>> {
+        match self {
+            Event::NodeName { ty, .. }
+            | Event::NodeEntry(
+                KdlNodeEntry::Argument { ty, .. } | KdlNodeEntry::Property { ty, .. },
+            ) => ty.as_mut(),
+            _ => None,
+        }
+    }
+
+    /// Converts the borrowed event into an owned one, eliminating the lifetime.
+    pub fn into_owned(self) -> Event<'static> {
+        match self {
+            Self::StartDocument => Event::StartDocument,
+            Self::EndDocument => Event::EndDocument,
+            Self::Indentation(amount) => Event::Indentation(amount),
+            Self::NodeName { ty, name } => Event::NodeName {
+                ty: owned_ty(ty),
+                name: name.into_owned(),
+            },
+            Self::NodeEntry(entry) => Event::NodeEntry(entry.into_owned()),
+            Self::NodeEnd { inline } => Event::NodeEnd { inline },
+            Self::Comment { text, kind } => Event::Comment {
+                text: Cow::Owned(text.into_owned()),
+                kind,
+            },
+            Self::SlashdashStart => Event::SlashdashStart,
+            Self::SlashdashEnd => Event::SlashdashEnd,
+            Self::Error => Event::Error,
+        }
+    }
 }
 
 pub type Text<'a> = Cow<'a, str>;
@@ -84,31 +228,112 @@ pub type Text<'a> = Cow<'a, str>;
 pub type Ranged<T> = (T, Range<usize>);
 type Item<T> = Option<Ranged<T>>;
 type ItemEvent<'text> = Item<Event<'text>>;
-type ParseResult<T> = Result<T, ParseErrorCause>;
+type ParseResult<T> = Result<T, error::Located>;
 
 pub(crate) fn item<T>(t: T, r: Range<usize>) -> Item<T> {
     Some((t, r))
 }
 
-#[derive(Default, Clone, Copy)]
+/// Changes which events a [`Parser`] emits.
+#[derive(Default, Clone, Copy, Debug, PartialEq)]
+pub struct ParserOptions {
+    /// Emits [`Event::Comment`] for each comment instead of skipping it.
+    pub comments: bool,
+    /// Emits items commented out with `/-` between [`Event::SlashdashStart`] and
+    /// [`Event::SlashdashEnd`] instead of skipping them.
+    pub slashdash: bool,
+    /// Recovers from errors by skipping the rest of the node they happened in, emitting
+    /// [`Event::Error`] and carrying on, instead of returning them.
+    pub recover: bool,
+}
+
+#[derive(Default, Clone)]
 pub struct Parser<'text> {
     acc: Acc<'text>,
     document_depth: usize,
     state: State,
+    options: ParserOptions,
+    /// The items currently commented out, along with the document depth they started at.
+    slashdashes: Vec<(Slashdash, usize)>,
+    /// Whether the last event ended the innermost slashdash, so [`Event::SlashdashEnd`] is next.
+    slashdash_ended: bool,
+    /// The errors recovered from so far.
+    errors: Vec<ParseError<'text>>,
+    /// An event that was parsed, but only emitted after recovering from an error found in it.
+    pending: ItemEvent<'text>,
+    /// Whether the text ended abruptly and was recovered from, so every open document ends.
+    ended_abruptly: bool,
+    /// The name of the last node found, which any children block that follows belongs to.
+    node_name: KdlString<'text>,
+    /// The children blocks still open, along with the name of their node and where their `{` is.
+    open_blocks: Vec<(KdlString<'text>, usize)>,
+    /// Where the text starts in the whole document, when it's only a piece of it.
+    origin: error::Origin,
+    /// Whether more text may follow, so events that reach the end of the text may still change.
+    partial: bool,
+    /// What cut the text short, such as invalid UTF-8, reported instead of running out of text.
+    truncated: Option<error::Located>,
 }
 
 impl<'text> Parser<'text> {
     pub fn new(source: &'text str) -> Self {
+        Self::with_options(source, ParserOptions::default())
+    }
+
+    pub fn with_options(source: &'text str, options: ParserOptions) -> Self {
         Self {
             acc: Acc::new(source),
+            options,
             ..Default::default()
         }
     }
 
+    /// Creates a parser for a document that wasn't decoded yet, such as the contents of a file.
+    ///
+    /// Only the text before the first invalid UTF-8 sequence is parsed, and the events before it
+    /// are emitted as usual. Once the parser reaches it, a [`ParseErrorCause::InvalidUtf8`] error
+    /// is returned, and no more events follow. Documents starting with the byte order mark of
+    /// another encoding, such as UTF-16, fail right away with a
+    /// [`ParseErrorCause::UnsupportedEncoding`] error instead. A UTF-8 byte order mark is skipped,
+    /// just like with [`Parser::new`].
+    pub fn from_bytes(bytes: &'text [u8]) -> Self {
+        Self::from_bytes_with_options(bytes, ParserOptions::default())
+    }
+
+    pub fn from_bytes_with_options(bytes: &'text [u8], options: ParserOptions) -> Self {
+        let (text, truncated) = match (string::foreign_bom(bytes), std::str::from_utf8(bytes)) {
+            (Some(encoding), _) => ("", Some(UnsupportedEncoding { encoding }.at(0))),
+            (None, Ok(text)) => (text, None),
+            (None, Err(error)) => {
+                let valid = &bytes[..error.valid_up_to()];
+                let text = std::str::from_utf8(valid)
+                    .expect("The bytes up to the error should be valid UTF-8");
+                (text, Some(InvalidUtf8.at(valid.len())))
+            }
+        };
+        Self {
+            partial: truncated.is_some(),
+            truncated,
+            ..Self::with_options(text, options)
+        }
+    }
+
     fn peek_next_event(&mut self) -> ParseResult<ItemEvent<'text>> {
+        if self.slashdash_ended {
+            self.slashdash_ended = false;
+            return Ok(item(Event::SlashdashEnd, 0..0));
+        }
+        let event = self.peek_state_event()?;
+        if let Some((event, _)) = &event {
+            self.slashdash_ended = self.check_slashdash_end(event);
+        }
+        Ok(event)
+    }
+
+    fn peek_state_event(&mut self) -> ParseResult<ItemEvent<'text>> {
         // Looks for indentation
         if !matches!(self.state, State::NodeEntries | State::DocumentEnd)
-            && let Some((ws, ws_range)) = self.acc.peek_blankspace()
+            && let Some((ws, ws_range)) = self.acc.peek_blankspace(!self.options.comments)?
             && !ws_range.is_empty()
         {
             return Ok(item(Event::Indentation(ws), ws_range));
@@ -119,53 +344,92 @@ impl<'text> Parser<'text> {
         tprintln!("{:?}", self.acc.remaining_text());
         match self.state {
             State::Initial => {
+                if let Some(bom) = self.acc.expect_sequence("\u{FEFF}") {
+                    self.acc.consume_range(&bom);
+                }
                 self.start_document();
                 self.document_depth = 0;
                 Ok(item(Event::StartDocument, 0..0))
             }
-            State::Final => return Ok(None),
+            State::Final => Ok(None),
             State::DocumentEnd => {
-                let c = self.acc.consume_next_char().ok_or_eof()?;
-                match self.check_node_end(c)? {
-                    Some(item) => {
+                self.consume_entry_space()?;
+                if let Some(comment) = self.check_comment()? {
+                    return Ok(Some(comment));
+                }
+                if let Some(slashdash) = self.check_slashdash()? {
+                    return Ok(Some(slashdash));
+                }
+                match self.acc.peek_char() {
+                    // The node's parent document ends right after its children.
+                    None | Some('}') => {
                         self.set_state(State::Document);
-                        Ok(item.into())
+                        Ok(item(Event::NodeEnd { inline: false }, 0..0))
+                    }
+                    // Only valid after slashdashed children, which may be followed by more.
+                    Some('{') => {
+                        self.open_block();
+                        Ok(item(Event::StartDocument, 0..1))
                     }
-                    None => Err(ParseErrorCause::Expected(error::Expected::LineEnd)),
+                    Some(c) => match self.check_node_end(c)? {
+                        Some(item) => {
+                            self.set_state(State::Document);
+                            Ok(item.into())
+                        }
+                        None => Err(ParseErrorCause::expected(error::Expected::LineEnd, c).into()),
+                    },
                 }
             }
             State::Document => {
                 // Check if the document has ended
                 if let Some(((), range)) = self.check_end() {
+                    let is_root = self.is_root_document();
                     self.end_document();
-                    if self.is_root_document() {
+                    if is_root {
                         self.set_state(State::Final);
                     }
                     return Ok(item(Event::EndDocument, range));
                 }
-                // TODO: parse type cast
-                let (name, range) = self.acc.peek_string()?;
+                if let Some(comment) = self.check_comment()? {
+                    return Ok(Some(comment));
+                }
+                if let Some(slashdash) = self.check_slashdash()? {
+                    return Ok(Some(slashdash));
+                }
+                match self.acc.peek_char() {
+                    None => return Err(self.unclosed_block().into()),
+                    Some('}') => return Err(UnmatchedClosingBrace.into()),
+                    _ => (),
+                }
+                let mut sub = self.acc.sub_accumulator();
+                let ty = sub.consume_annotation()?;
+                let name = sub
+                    .consume_string()
+                    .map_err(|error| error.expecting(error::Expected::NodeName))?;
                 self.set_state(State::NodeEntries);
-                Ok(item(Event::NodeName(name), range))
+                self.node_name = name.clone();
+                Ok(item(Event::NodeName { ty, name }, sub.range()))
             }
             State::NodeEntries => {
                 // check for children start
-                let _ = self.acc.consume_whitespace()?;
+                self.consume_entry_space()?;
+                if let Some(comment) = self.check_comment()? {
+                    return Ok(Some(comment));
+                }
+                if let Some(slashdash) = self.check_slashdash()? {
+                    return Ok(Some(slashdash));
+                }
                 let Some(c) = self.acc.peek_char() else {
                     self.set_state(State::Document);
-                    return if self.document_depth == 0 {
-                        Ok(item(Event::NodeEnd { inline: false }, 0..0))
-                    } else {
-                        Err(NeedsMoreData)
-                    };
+                    return Ok(item(Event::NodeEnd { inline: false }, 0..0));
                 };
-                let c_range = 0..1;
                 if c == '{' {
-                    self.start_document();
-                    return Ok(item(Event::StartDocument, c_range));
+                    self.open_block();
+                    return Ok(item(Event::StartDocument, 0..1));
                 } else if c == '}' {
-                    self.end_document();
-                    return Ok(item(Event::EndDocument, c_range));
+                    // The node ends along with its parent document.
+                    self.set_state(State::Document);
+                    return Ok(item(Event::NodeEnd { inline: false }, 0..0));
                 }
 
                 if let Some(node_end) = self.check_node_end(c)? {
@@ -173,39 +437,123 @@ impl<'text> Parser<'text> {
                     return Ok(Some(node_end));
                 }
 
-                // TODO: parse type cast
                 let mut sub = self.acc.sub_accumulator();
+                let ty = sub.consume_annotation()?;
                 let value = sub.consume_value()?;
                 if let Some(c) = sub.peek_char()
                     && is_equals(c)
                 {
+                    if ty.is_some() {
+                        return Err(AnnotatedKey.into());
+                    }
                     sub.consume_next_char();
                     // parse property
                     match value {
                         KdlValue::String(key) => {
+                            let ty = sub.consume_annotation()?;
                             let value = sub.consume_value()?;
                             return Ok(item(
-                                Event::NodeEntry(KdlNodeEntry::Property { key, value }),
+                                Event::NodeEntry(KdlNodeEntry::Property { key, ty, value }),
                                 sub.range(),
                             ));
                         }
                         _ => {
                             return Err(InvalidKey {
                                 value: value.into_owned(),
-                            })
+                            }
+                            .into());
                         }
                     }
                 }
                 // parse argument
-                return Ok(item(
-                    Event::NodeEntry(KdlNodeEntry::Argument(value)),
+                Ok(item(
+                    Event::NodeEntry(KdlNodeEntry::Argument { ty, value }),
                     sub.range(),
-                ));
+                ))
+            }
+        }
+    }
+
+    /// Looks for comments, returning the first one as an event if [`ParserOptions::comments`] is
+    /// enabled, or skipping all of them (and the whitespace between them) otherwise.
+    fn check_comment(&mut self) -> ParseResult<ItemEvent<'text>> {
+        while let Some(((text, kind), range)) = self.acc.peek_comment()? {
+            if self.options.comments {
+                return Ok(item(Event::Comment { text, kind }, range));
             }
+            self.acc.consume_range(&range);
+            self.consume_whitespace()?;
+        }
+        Ok(None)
+    }
+
+    /// Looks for a `/-`, making sure there's something after it to comment out.
+    fn check_slashdash(&mut self) -> ParseResult<ItemEvent<'text>> {
+        let Some(range) = self.acc.expect_sequence("/-") else {
+            return Ok(None);
+        };
+        let mut target = self.acc.sub_accumulator();
+        target.consume_range(&range);
+        if let Some((_, space)) = target.peek_blankspace(true)? {
+            target.consume_range(&space);
         }
+        let slashdash = match (self.state, target.peek_char()) {
+            // A slashdash can't comment out another one.
+            _ if target.expect_sequence("/-").is_some() => None,
+            (_, None | Some('}')) => None,
+            (State::Document, Some(_)) => Some(Slashdash::Node),
+            (_, Some('{')) => Some(Slashdash::Children),
+            (State::NodeEntries, Some(c)) if c != ';' => Some(Slashdash::Entry),
+            _ => None,
+        };
+        let slashdash = slashdash.ok_or(DanglingSlashdash)?;
+        self.slashdashes.push((slashdash, self.document_depth));
+        Ok(item(Event::SlashdashStart, range))
     }
 
-    pub fn check_node_end(&self, c: char) -> ParseResult<ItemEvent<'text>> {
+    /// Checks whether `event` ends the innermost slashdash, popping it if so.
+    fn check_slashdash_end(&mut self, event: &Event) -> bool {
+        let Some(&(slashdash, depth)) = self.slashdashes.last() else {
+            return false;
+        };
+        let ended = match (slashdash, event) {
+            (Slashdash::Node, Event::NodeEnd { .. }) => self.document_depth == depth,
+            (Slashdash::Entry, Event::NodeEntry(_)) => true,
+            (Slashdash::Children, Event::EndDocument) => self.document_depth == depth,
+            _ => false,
+        };
+        if ended {
+            self.slashdashes.pop();
+        }
+        ended
+    }
+
+    /// Consumes the space before an entry or a children block. Line breaks are only allowed
+    /// between a `/-` and the item it comments out, as they end the node otherwise.
+    fn consume_entry_space(&mut self) -> ParseResult<()> {
+        let after_slashdash = matches!(
+            self.slashdashes.last(),
+            Some(&(Slashdash::Entry | Slashdash::Children, depth)) if depth == self.document_depth
+        );
+        if after_slashdash {
+            if let Some((_, range)) = self.acc.peek_blankspace(!self.options.comments)? {
+                self.acc.consume_range(&range);
+            }
+        } else {
+            self.consume_whitespace()?;
+        }
+        Ok(())
+    }
+
+    /// Consumes whitespace, keeping errors relative to the current position like the other checks.
+    fn consume_whitespace(&mut self) -> ParseResult<()> {
+        let mut space = self.acc.sub_accumulator();
+        space.consume_whitespace()?;
+        self.acc.consume_range(&space.range());
+        Ok(())
+    }
+
+    fn check_node_end(&self, c: char) -> ParseResult<ItemEvent<'text>> {
         if string::is_newline(c) {
             Ok(item(Event::NodeEnd { inline: false }, 0..0))
         } else if c == ';' {
@@ -216,21 +564,212 @@ impl<'text> Parser<'text> {
     }
 
     pub fn next_event_borrowed(&mut self) -> Result<ItemEvent<'text>, ParseError<'text>> {
+        loop {
+            let evt = match self.pending.take() {
+                Some(evt) => Ok(Some(evt)),
+                None => self.parse_event(),
+            };
+            if self.needs_more_text(&evt) {
+                return Err(self.text_ended());
+            }
+            let evt = match evt {
+                Err(error) if self.options.recover => Ok(Some(self.recover(error))),
+                evt => evt,
+            };
+            if self.needs_more_text(&evt) {
+                return Err(self.text_ended());
+            }
+            tprintln!("RESULT:\n{:?}\n", evt);
+            if let Ok(Some((evt, _))) = &evt
+                && self.is_slashdashed(evt)
+            {
+                continue;
+            }
+            return evt.map(|evt| evt.map(|evt| self.locate(evt)));
+        }
+    }
+
+    /// The error for running out of text before knowing what the next event is.
+    fn text_ended(&mut self) -> ParseError<'text> {
+        match self.truncated.clone() {
+            // Nothing past what cut the text short can be parsed, so there are no more events.
+            Some(error) => {
+                self.set_state(State::Final);
+                self.pending = None;
+                self.slashdash_ended = false;
+                self.error(error)
+            }
+            None => self.error(NeedsMoreData.at(self.acc.base().len())),
+        }
+    }
+
+    /// Whether `evt` may turn out differently once more text follows, if the text is
+    /// [`partial`](Self::partial).
+    fn needs_more_text(&self, evt: &Result<ItemEvent<'text>, ParseError<'text>>) -> bool {
+        let text = self.acc.base();
+        let rest = match evt {
+            _ if !self.partial => return false,
+            Ok(evt) => {
+                return evt
+                    .as_ref()
+                    .is_some_and(|(_, range)| range.end == text.len());
+            }
+            Err(error)
+                if matches!(
+                    error.cause,
+                    NeedsMoreData
+                        | ParseErrorCause::Expected {
+                            found: error::Found::Eof,
+                            ..
+                        }
+                ) =>
+            {
+                return true;
+            }
+            Err(error) => {
+                let end = error.span().end - self.origin.offset;
+                text.get(end..).unwrap_or_default()
+            }
+        };
+        // An error is only certain once something other than blank space follows it.
+        !matches!(
+            Acc::new(rest).peek_blankspace(true),
+            Ok(Some((_, range))) if range.end < rest.len()
+        )
+    }
+
+    /// Moves an event's ranges from the piece of text being parsed to the whole document.
+    fn locate(&self, (mut event, range): Ranged<Event<'text>>) -> Ranged<Event<'text>> {
+        if let Some((_, ty_range)) = event.ty_mut() {
+            *ty_range = ty_range.offset_by(self.origin.offset);
+        }
+        (event, range.clone().offset_by(self.origin.offset))
+    }
+
+    /// Parses the next event, making its range absolute and advancing past it.
+    fn parse_event(&mut self) -> Result<ItemEvent<'text>, ParseError<'text>> {
+        let start = self.acc.end;
         let mut evt = self.peek_next_event();
-        if let Ok(Some((_evt, range))) = &mut evt {
+        if let Ok(Some((evt, range))) = &mut evt {
             // Updates the range to be absolute
             *range = range.offset_by(self.acc.end);
+            if let Some((_, ty_range)) = evt.ty_mut() {
+                *ty_range = ty_range.offset_by(self.acc.end);
+            }
 
             // Advances the current index past the parsed event.
             self.acc.set_end(range.end);
         }
-        let evt = evt.map_err(|cause| ParseError {
-            cause,
-            at: self.acc.end,
-            source: self.acc.base().into(),
-        });
-        tprintln!("RESULT:\n{:?}\n", evt);
-        evt
+        let evt = evt.map_err(|error| self.error(error.offset_by(self.acc.end)))?;
+
+        // Everything parsed so far must be made of code points allowed in a document.
+        let parsed = &self.acc.base()[start..self.acc.end];
+        let at_document_start = start == 0 && self.origin.offset == 0;
+        if let Some(error) = string::find_disallowed(parsed, at_document_start) {
+            // Nothing else is wrong with the event, so it can still be emitted after the error.
+            self.pending = evt;
+            return Err(self.error(error.offset_by(start)));
+        }
+        Ok(evt)
+    }
+
+    fn error(&self, error: error::Located) -> ParseError<'text> {
+        ParseError {
+            cause: error.cause,
+            at: error.at + self.origin.offset,
+            source: error::Source {
+                text: self.acc.base().into(),
+                origin: self.origin,
+            },
+        }
+    }
+
+    /// Records `error` and skips what's left of the node it happened in, returning an
+    /// [`Event::Error`] spanning the skipped text.
+    fn recover(&mut self, error: ParseError<'text>) -> Ranged<Event<'text>> {
+        let range = if self.pending.is_some() {
+            // The event was fully parsed, so only the offending code point is marked.
+            let at = error.at - self.origin.offset;
+            let len = self.acc.base()[at..]
+                .chars()
+                .next()
+                .map_or(0, char::len_utf8);
+            at..at + len
+        } else {
+            // An item that failed to parse is still the one commented out.
+            let failed_item = match (self.state, self.slashdashes.last()) {
+                (State::NodeEntries, Some((Slashdash::Entry, _))) => true,
+                (State::Document, Some(&(Slashdash::Node, depth))) => depth == self.document_depth,
+                _ => false,
+            };
+            if failed_item {
+                self.slashdashes.pop();
+                self.slashdash_ended = true;
+            }
+            self.skip_node()
+        };
+        self.errors.push(error);
+        (Event::Error, range)
+    }
+
+    /// Skips to the terminator of the current node or to the `}` ending its document, whichever
+    /// comes first, along with any children blocks on the way.
+    fn skip_node(&mut self) -> Range<usize> {
+        let start = self.acc.end;
+        let mut nesting = 0usize;
+        while let Some(c) = self.acc.peek_char() {
+            match c {
+                '}' | ';' if nesting == 0 => break,
+                c if string::is_newline(c) && nesting == 0 => break,
+                '{' => nesting += 1,
+                '}' => nesting -= 1,
+                _ => (),
+            }
+            self.acc.consume_next_char();
+        }
+        // Without a node name, there's no node to end, so its `;` is skipped too. Anything else
+        // that can't start a node, like an extra `}` or the newline before a broken comment at the
+        // start of the text, is also skipped so parsing moves forward.
+        if matches!(self.state, State::Initial | State::Document)
+            && (self.acc.end == start || self.acc.peek_char() == Some(';'))
+        {
+            self.acc.consume_next_char();
+        }
+        // Nothing is left to recover, so the documents still open can't be closed normally.
+        if self.acc.remaining_text().is_empty() {
+            self.ended_abruptly = true;
+        }
+        start..self.acc.end
+    }
+
+    /// Indexes the lines of the text being parsed, so byte offsets can be turned into positions.
+    pub fn line_index(&self) -> LineIndex<'text> {
+        LineIndex::new(self.acc.base())
+    }
+
+    /// Turns this parser into one that emits the [`Span`] of each event instead of just its byte
+    /// range.
+    pub fn into_positioned(self) -> PositionedParser<'text> {
+        PositionedParser::new(self)
+    }
+
+    /// The errors recovered from so far, if [`ParserOptions::recover`] is enabled.
+    pub fn errors(&self) -> &[ParseError<'text>] {
+        &self.errors
+    }
+
+    /// Takes the errors recovered from so far, so they can be reported together.
+    pub fn into_diagnostics(self) -> Diagnostics<'text> {
+        self.errors.into_iter().collect()
+    }
+
+    /// Whether `event` was commented out and must be skipped.
+    fn is_slashdashed(&self, event: &Event) -> bool {
+        !self.options.slashdash
+            && !matches!(event, Event::Error)
+            && (matches!(event, Event::SlashdashStart | Event::SlashdashEnd)
+                || !self.slashdashes.is_empty()
+                || self.slashdash_ended)
     }
 
     pub fn next_event(&mut self) -> Result<ItemEvent<'text>, ParseError<'static>> {
@@ -246,13 +785,27 @@ impl<'text> Parser<'text> {
         self.document_depth += 1;
     }
 
+    /// Starts the children block of the last node found, whose `{` is next.
+    fn open_block(&mut self) {
+        let at = self.origin.offset + self.acc.end;
+        self.open_blocks.push((self.node_name.clone(), at));
+        self.start_document();
+    }
+
+    /// The error for a text that ends while the innermost children block is still open.
+    fn unclosed_block(&self) -> ParseErrorCause {
+        let (node, opened_at) = self.open_blocks.last().cloned().unwrap_or_default();
+        let expected = error::Expected::ClosingBrace {
+            node: node.string.into_owned(),
+            opened_at,
+        };
+        ParseErrorCause::expected(expected, None)
+    }
+
     fn end_document(&mut self) {
+        self.open_blocks.pop();
         self.document_depth = self.document_depth.saturating_sub(1);
-        if self.is_root_document() {
-            self.set_state(State::Document);
-        } else {
-            self.set_state(State::DocumentEnd);
-        }
+        self.set_state(State::DocumentEnd);
     }
 
     fn set_state(&mut self, new_state: State) {
@@ -262,13 +815,10 @@ impl<'text> Parser<'text> {
 
     fn check_end(&self) -> Item<()> {
         let rem = self.acc.remaining_text();
-        if self.document_depth == 0 && rem.is_empty() {
-            return item((), 0..rem.len());
+        if self.document_depth == 0 || (rem.is_empty() && self.ended_abruptly) {
+            rem.is_empty().then_some(((), 0..0))
         } else {
-            let mut subacc = self.acc.sub_accumulator();
-            subacc.consume_next_char().filter(|c| *c == '}')?;
-            subacc.consume_whitespace().ok()?;
-            return item((), subacc.range());
+            self.acc.expect_sequence("}").map(|range| ((), range))
         }
     }
 }
@@ -281,3 +831,23 @@ impl<'text> std::iter::Iterator for Parser<'text> {
 }
 
 impl<'text> std::iter::FusedIterator for Parser<'text> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recovering_always_moves_forward() {
+        let options = ParserOptions {
+            comments: false,
+            slashdash: false,
+            recover: true,
+        };
+        for text in ["\n/*", "\r\n/*", " \u{2028}/*", "//x\r\n/*", "//*/\n/*k="] {
+            let mut parser = Parser::with_options(text, options);
+            parser.by_ref().take(100).for_each(drop);
+            assert!(parser.next().is_none(), "{text:?} didn't end");
+            assert!(!parser.errors().is_empty(), "{text:?} had no errors");
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::borrow::Cow;
+use std::fmt::Display;
+
+use ownable::IntoOwned;
+
+use crate::error;
+use crate::prelude::*;
+use crate::string::{is_digit, is_non_identifier};
+use crate::{ParseResult, Ranged, Text};
+
+pub(crate) const fn is_sign(c: char) -> bool {
+    matches!(c, '+' | '-')
+}
+
+/// The value a [`KdlNumber`] was parsed into.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Repr {
+    Integer(i128),
+    Float(f64),
+}
+
+/// A number, as found in a KDL document.
+///
+/// Keeps the original lexeme around, so no precision is lost regardless of how the number is
+/// later used. Integers (including radix ones like `0xFF`) that fit in an [`i128`] are stored
+/// losslessly, everything else is stored as a [`f64`].
+///
+/// Two numbers are equal if their lexemes are, so `1.0` and `1.00` are different numbers.
+#[derive(IntoOwned, Clone, Debug)]
+pub struct KdlNumber<'text> {
+    lexeme: Text<'text>,
+    #[ownable(clone)]
+    repr: Repr,
+}
+
+impl<'text> KdlNumber<'text> {
+    /// Parses a number from its textual representation, e.g. `-1_000`, `0xFF`, `1.5e10` or `#inf`.
+    pub fn parse(lexeme: &'text str) -> Result<Self, ParseErrorCause> {
+        if let Some(number) = Self::from_keyword(lexeme) {
+            return Ok(number);
+        }
+        let mut acc = Acc::new(lexeme);
+        let (number, range) = acc.peek_number().map_err(|error| error.cause)?;
+        acc.consume_range(&range);
+        match acc.peek_char() {
+            Some(c) => Err(InvalidNumberCharacter { c }),
+            None => Ok(number),
+        }
+    }
+
+    /// Creates a number from one of the `#inf`, `#-inf` and `#nan` keywords.
+    pub(crate) fn from_keyword(keyword: &'text str) -> Option<Self> {
+        let value = match keyword {
+            "#inf" => f64::INFINITY,
+            "#-inf" => f64::NEG_INFINITY,
+            "#nan" => f64::NAN,
+            _ => return None,
+        };
+        Some(Self {
+            lexeme: Cow::Borrowed(keyword),
+            repr: Repr::Float(value),
+        })
+    }
+
+    /// The number exactly as it was written in the source.
+    pub fn lexeme(&self) -> &str {
+        &self.lexeme
+    }
+
+    /// Whether the number was written, and could be stored, as an integer.
+    pub fn is_integer(&self) -> bool {
+        matches!(self.repr, Repr::Integer(_))
+    }
+
+    /// Returns the number as an [`i128`], if it is an integer.
+    pub fn as_i128(&self) -> Option<i128> {
+        match self.repr {
+            Repr::Integer(v) => Some(v),
+            Repr::Float(_) => None,
+        }
+    }
+
+    /// Returns the number as an [`i64`], if it is an integer that fits in one.
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_i128()?.try_into().ok()
+    }
+
+    /// Returns the number as an [`u64`], if it is an integer that fits in one.
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_i128()?.try_into().ok()
+    }
+
+    /// Returns the number as a [`f64`]. Big integers may lose precision.
+    pub fn as_f64(&self) -> f64 {
+        match self.repr {
+            Repr::Integer(v) => v as f64,
+            Repr::Float(v) => v,
+        }
+    }
+}
+
+impl PartialEq for KdlNumber<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.lexeme == other.lexeme
+    }
+}
+
+impl Display for KdlNumber<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.lexeme.fmt(f)
+    }
+}
+
+/// Numbers are (de)serialized as their lexeme, since that's their only lossless representation.
+#[cfg(feature = "serde")]
+impl serde::Serialize for KdlNumber<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.lexeme)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for KdlNumber<'_> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let lexeme = String::deserialize(deserializer)?;
+        KdlNumber::parse(&lexeme)
+            .map(|number| number.into_owned())
+            .map_err(|cause| {
+                serde::de::Error::custom(format!("invalid number {lexeme:?}: {cause:?}"))
+            })
+    }
+}
+
+pub(crate) trait ParseNumber<'text>: Buffer<'text> {
+    /// Checks whether the remaining text starts like a number, that is, with a digit or with a sign
+    /// followed by a digit.
+    fn starts_number(&self) -> bool {
+        let mut chars = self.remaining_text().chars();
+        match chars.next() {
+            Some(c) if is_digit(c) => true,
+            Some(c) if is_sign(c) => chars.next().is_some_and(is_digit),
+            _ => false,
+        }
+    }
+
+    /// Consumes an integer of the given `radix`: a digit followed by any amount of digits or `_`.
+    fn consume_integer(&mut self, radix: u32) -> ParseResult<()> {
+        let Some(c) = self.peek_char() else {
+            return Err(
+                ParseErrorCause::expected(error::Expected::Digit, error::Found::Eof).at(self.end()),
+            );
+        };
+        if c.to_digit(radix).is_none() {
+            return Err(InvalidNumberCharacter { c }.at(self.end()));
+        }
+        while let Some(c) = self.consume_next_char() {
+            if c != '_' && c.to_digit(radix).is_none() {
+                self.unconsume_char(c);
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn peek_number(&self) -> ParseResult<Ranged<KdlNumber<'text>>> {
+        let mut acc = self.sub_accumulator();
+
+        let negative = match acc.peek_char() {
+            Some(c) if is_sign(c) => {
+                acc.consume_next_char();
+                c == '-'
+            }
+            _ => false,
+        };
+
+        let radix = [("0x", 16), ("0o", 8), ("0b", 2)]
+            .into_iter()
+            .find_map(|(prefix, radix)| Some((acc.expect_sequence(prefix)?, radix)));
+
+        let repr = if let Some((prefix, radix)) = radix {
+            acc.consume_range(&prefix);
+            let start = acc.end();
+            acc.consume_integer(radix)?;
+            let digits = acc.text()[start..].replace('_', "");
+            let sign = if negative { "-" } else { "" };
+            match i128::from_str_radix(&format!("{sign}{digits}"), radix) {
+                Ok(value) => Repr::Integer(value),
+                // Too big even for an `i128`, so the best that can be done is a float.
+                Err(_) => {
+                    let value = digits
+                        .chars()
+                        .filter_map(|c| c.to_digit(radix))
+                        .fold(0.0, |value, digit| value * radix as f64 + digit as f64);
+                    Repr::Float(if negative { -value } else { value })
+                }
+            }
+        } else {
+            let mut is_float = false;
+            acc.consume_integer(10)?;
+            if acc.peek_char() == Some('.') {
+                is_float = true;
+                acc.consume_next_char();
+                acc.consume_integer(10)?;
+            }
+            if let Some(c @ ('e' | 'E')) = acc.peek_char() {
+                is_float = true;
+                acc.advance_bytes(c.len_utf8());
+                if let Some(c) = acc.peek_char()
+                    && is_sign(c)
+                {
+                    acc.advance_bytes(c.len_utf8());
+                }
+                acc.consume_integer(10)?;
+            }
+            let lexeme = acc.text().replace('_', "");
+            match lexeme.parse::<i128>() {
+                Ok(value) if !is_float => Repr::Integer(value),
+                _ => Repr::Float(
+                    lexeme
+                        .parse::<f64>()
+                        .expect("A valid decimal number should always be parseable"),
+                ),
+            }
+        };
+
+        // Numbers must be followed by something that can't be part of an identifier.
+        if let Some(c) = acc.peek_char()
+            && !is_non_identifier(c)
+        {
+            return Err(InvalidNumberCharacter { c }.at(acc.end()));
+        }
+
+        let number = KdlNumber {
+            lexeme: Cow::Borrowed(acc.text()),
+            repr,
+        };
+        Ok((number, acc.range()))
+    }
+}
+
+impl<'text, B> ParseNumber<'text> for B where B: Buffer<'text> {}
@@ -1,18 +1,108 @@
 use crate::{
-    prelude::*, string::ParseString, utils::OptionExt, Item, KdlValue, ParseResult, Ranged,
+    Item, KdlNumber, KdlString, KdlValue, ParseResult, Ranged, error, item,
+    number::ParseNumber,
+    prelude::*,
+    string::{ParseString, is_non_identifier},
 };
 
-pub(crate) trait Parse<'text>: Buffer<'text> + ParseString<'text> {
+pub(crate) trait Parse<'text>:
+    Buffer<'text> + ParseString<'text> + ParseNumber<'text>
+{
+    /// Parses one of the `#`-prefixed keywords: `#true`, `#false`, `#null`, `#inf`, `#-inf` or `#nan`.
+    fn peek_keyword(&self) -> ParseResult<Ranged<KdlValue<'text>>> {
+        let mut acc = self.sub_accumulator();
+        acc.consume_next_char();
+        while let Some(c) = acc.consume_next_char() {
+            if is_non_identifier(c) {
+                acc.unconsume_char(c);
+                break;
+            }
+        }
+        let value = match acc.text() {
+            "#true" => KdlValue::Bool(true),
+            "#false" => KdlValue::Bool(false),
+            "#null" => KdlValue::Null,
+            keyword => match KdlNumber::from_keyword(keyword) {
+                Some(number) => KdlValue::Num(number),
+                None => {
+                    return Err(UnknownKeyword {
+                        keyword: keyword.into(),
+                    }
+                    .into());
+                }
+            },
+        };
+        Ok((value, acc.range()))
+    }
+
     fn peek_value(&self) -> ParseResult<Ranged<KdlValue<'text>>> {
+        if self.peek_char() == Some('#') && !self.starts_raw_string() {
+            return self.peek_keyword();
+        }
+        if self.starts_number() {
+            return self
+                .peek_number()
+                .map(|(number, range)| (KdlValue::Num(number), range));
+        }
         self.peek_string()
             .map(|(string, range)| (KdlValue::String(string), range))
+            .map_err(|error| error.expecting(error::Expected::Value))
+    }
+
+    /// Looks for a type annotation, such as the `(u8)` in `(u8)10`, returning its type.
+    fn peek_annotation(&self) -> ParseResult<Item<KdlString<'text>>> {
+        let mut acc = self.sub_accumulator();
+        if acc.peek_char() != Some('(') {
+            return Ok(None);
+        }
+        acc.consume_next_char();
+        acc.consume_whitespace()?;
+        let (ty, range) = acc
+            .peek_string()
+            .map_err(|error| error.offset_by(acc.end()))?;
+        acc.consume_range(&range);
+        acc.consume_whitespace()?;
+        match acc.peek_char() {
+            Some(')') => (),
+            found => {
+                let cause = ParseErrorCause::expected(error::Expected::AnnotationEnd, found);
+                return Err(cause.at(acc.end()));
+            }
+        }
+        acc.consume_next_char();
+        Ok(item(ty, acc.range()))
+    }
+
+    /// Consumes a type annotation along with the whitespace after it. The annotation's range is
+    /// relative to the start of the text, so it can be reported along with the entry or node name.
+    fn consume_annotation(&mut self) -> ParseResult<Item<KdlString<'text>>> {
+        let start = self.end();
+        let Some((ty, mut range)) = self
+            .peek_annotation()
+            .map_err(|error| error.offset_by(start))?
+        else {
+            return Ok(None);
+        };
+        self.consume_range(&range);
+        self.consume_whitespace()?;
+        Ok(item(ty, range.offset_by(start)))
+    }
+
+    fn consume_string(&mut self) -> ParseResult<KdlString<'text>> {
+        let (string, range) = self
+            .peek_string()
+            .map_err(|error| error.offset_by(self.end()))?;
+        self.consume_range(&range);
+        Ok(string)
     }
 
     fn consume_value(&mut self) -> ParseResult<KdlValue<'text>> {
-        let (value, range) = self.peek_value()?;
+        let (value, range) = self
+            .peek_value()
+            .map_err(|error| error.offset_by(self.end()))?;
         self.consume_range(&range);
         Ok(value)
     }
 }
 
-impl<'text, B> Parse<'text> for B where B: Buffer<'text> + ParseString<'text> {}
+impl<'text, B> Parse<'text> for B where B: Buffer<'text> + ParseString<'text> + ParseNumber<'text> {}
@@ -0,0 +1,292 @@
+use std::ops::Range;
+
+use crate::string::is_newline;
+use crate::{Event, ParseError, Parser};
+
+/// A line and column in a document, both starting at 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Position {
+    pub line: usize,
+    /// The column, counted in characters (Unicode scalar values).
+    pub column: usize,
+    /// The column, counted in UTF-16 code units, as most editors and the Language Server Protocol
+    /// do.
+    pub utf16_column: usize,
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A byte range along with the positions it starts and ends at.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Span {
+    pub range: Range<usize>,
+    pub start: Position,
+    pub end: Position,
+}
+
+/// Converts byte offsets in a document into [`Position`]s.
+///
+/// Lines and characters longer than a byte are found once, when the index is built, so each
+/// conversion only takes a few binary searches. Any KDL newline ends a line, with `\r\n` counting
+/// as a single one.
+#[derive(Clone, Debug)]
+pub struct LineIndex<'text> {
+    text: &'text str,
+    /// The byte offset each line starts at.
+    line_starts: Vec<usize>,
+    /// The characters that take more than a byte, in order.
+    wide_chars: Vec<WideChar>,
+}
+
+/// A character that takes more than a byte, so columns after it are behind byte offsets.
+#[derive(Clone, Copy, Debug)]
+struct WideChar {
+    /// The byte range of the character.
+    start: usize,
+    end: usize,
+    /// How many more bytes than characters the text has up to the end of this character.
+    extra_bytes: usize,
+    /// How many more UTF-16 code units than characters the text has up to the end of this
+    /// character.
+    extra_utf16: usize,
+}
+
+impl<'text> LineIndex<'text> {
+    pub fn new(text: &'text str) -> Self {
+        let mut line_starts = vec![0];
+        let mut wide_chars: Vec<WideChar> = Vec::new();
+        let mut chars = text.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if !c.is_ascii() {
+                let (extra_bytes, extra_utf16) = wide_chars
+                    .last()
+                    .map_or((0, 0), |last| (last.extra_bytes, last.extra_utf16));
+                wide_chars.push(WideChar {
+                    start: i,
+                    end: i + c.len_utf8(),
+                    extra_bytes: extra_bytes + c.len_utf8() - 1,
+                    extra_utf16: extra_utf16 + c.len_utf16() - 1,
+                });
+            }
+            if c == '\r' && chars.next_if(|&(_, c)| c == '\n').is_some() {
+                line_starts.push(i + 2);
+            } else if is_newline(c) {
+                line_starts.push(i + c.len_utf8());
+            }
+        }
+        Self {
+            text,
+            line_starts,
+            wide_chars,
+        }
+    }
+
+    /// The text this index was built from.
+    pub fn text(&self) -> &'text str {
+        self.text
+    }
+
+    /// How many lines the text has. An empty text still has one line.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// The position of the byte at `offset`. Offsets past the end of the text are clamped to it,
+    /// and ones inside a character point at that character.
+    pub fn position(&self, offset: usize) -> Position {
+        let offset = offset.min(self.text.len());
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        let line_start = self.line_starts[line - 1];
+        // The wide characters before the line, and before the offset.
+        let before_line = self.wide_chars.partition_point(|c| c.start < line_start);
+        let before_offset = self.wide_chars.partition_point(|c| c.end <= offset);
+        let (line_bytes, line_utf16) = self.extra_before(before_line);
+        let (offset_bytes, offset_utf16) = self.extra_before(before_offset);
+        // The bytes of the character the offset is inside of, if any, don't make a column.
+        let inside = self
+            .wide_chars
+            .get(before_offset)
+            .map_or(0, |c| offset.saturating_sub(c.start));
+        let column = offset - line_start - inside - (offset_bytes - line_bytes) + 1;
+        let utf16_column = column + (offset_utf16 - line_utf16);
+        Position {
+            line,
+            column,
+            utf16_column,
+        }
+    }
+
+    /// How many more bytes and UTF-16 code units than characters the text has before the wide
+    /// character at `index`.
+    fn extra_before(&self, index: usize) -> (usize, usize) {
+        match index.checked_sub(1) {
+            Some(last) => (
+                self.wide_chars[last].extra_bytes,
+                self.wide_chars[last].extra_utf16,
+            ),
+            None => (0, 0),
+        }
+    }
+
+    /// The positions `range` starts and ends at.
+    pub fn span(&self, range: Range<usize>) -> Span {
+        Span {
+            start: self.position(range.start),
+            end: self.position(range.end),
+            range,
+        }
+    }
+
+    /// The position an error happened at.
+    pub fn error_position(&self, error: &ParseError) -> Position {
+        self.position(error.at)
+    }
+}
+
+/// An iterator over the events of a [`Parser`] along with their [`Span`]s, created with
+/// [`Parser::into_positioned`].
+#[derive(Clone)]
+pub struct PositionedParser<'text> {
+    parser: Parser<'text>,
+    index: LineIndex<'text>,
+}
+
+impl<'text> PositionedParser<'text> {
+    pub(crate) fn new(parser: Parser<'text>) -> Self {
+        Self {
+            index: parser.line_index(),
+            parser,
+        }
+    }
+
+    /// The index used to find the positions, which can also locate errors.
+    pub fn line_index(&self) -> &LineIndex<'text> {
+        &self.index
+    }
+
+    /// The parser the events come from.
+    pub fn parser(&self) -> &Parser<'text> {
+        &self.parser
+    }
+
+    pub fn next_event(&mut self) -> Result<Option<(Event<'text>, Span)>, ParseError<'static>> {
+        let event = self.parser.next_event()?;
+        Ok(event.map(|(event, range)| (event, self.index.span(range))))
+    }
+}
+
+impl<'text> Iterator for PositionedParser<'text> {
+    type Item = Result<(Event<'text>, Span), ParseError<'static>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+impl std::iter::FusedIterator for PositionedParser<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(line: usize, column: usize, utf16_column: usize) -> Position {
+        Position {
+            line,
+            column,
+            utf16_column,
+        }
+    }
+
+    #[test]
+    fn counts_utf16_columns_outside_the_bmp() {
+        // '😀' takes 4 bytes and 2 UTF-16 code units, 'é' takes 2 bytes and 1 code unit.
+        let index = LineIndex::new("a😀é b");
+        assert_eq!(index.position(0), position(1, 1, 1));
+        assert_eq!(index.position(1), position(1, 2, 2));
+        assert_eq!(index.position(5), position(1, 3, 4));
+        assert_eq!(index.position(7), position(1, 4, 5));
+        assert_eq!(index.position(8), position(1, 5, 6));
+    }
+
+    #[test]
+    fn offsets_inside_a_character_point_at_it() {
+        let index = LineIndex::new("a😀b");
+        for offset in 1..5 {
+            assert_eq!(index.position(offset), position(1, 2, 2));
+        }
+        assert_eq!(index.position(5), position(1, 3, 4));
+    }
+
+    #[test]
+    fn wide_characters_only_shift_columns_on_their_line() {
+        let index = LineIndex::new("é😀\n\u{2028}a😀é\u{2028}\nb");
+        assert_eq!(index.position(6), position(1, 3, 4));
+        assert_eq!(index.position(7), position(2, 1, 1));
+        // Inside the `\u{2028}` that ends the second line.
+        assert_eq!(index.position(8), position(2, 1, 1));
+        assert_eq!(index.position(10), position(3, 1, 1));
+        assert_eq!(index.position(11), position(3, 2, 2));
+        assert_eq!(index.position(13), position(3, 2, 2));
+        assert_eq!(index.position(15), position(3, 3, 4));
+        assert_eq!(index.position(16), position(3, 3, 4));
+        assert_eq!(index.position(17), position(3, 4, 5));
+        assert_eq!(index.position(20), position(4, 1, 1));
+        assert_eq!(index.position(22), position(5, 2, 2));
+    }
+
+    #[test]
+    fn crlf_is_a_single_newline() {
+        let index = LineIndex::new("a\r\nb\rc\nd\u{2028}e");
+        assert_eq!(index.line_count(), 5);
+        assert_eq!(index.position(1), position(1, 2, 2));
+        // The `\n` of a `\r\n` is still on the line it ends.
+        assert_eq!(index.position(2), position(1, 3, 3));
+        assert_eq!(index.position(3), position(2, 1, 1));
+        assert_eq!(index.position(5), position(3, 1, 1));
+        assert_eq!(index.position(7), position(4, 1, 1));
+        assert_eq!(index.position(11), position(5, 1, 1));
+    }
+
+    #[test]
+    fn offsets_at_or_past_the_end() {
+        let index = LineIndex::new("node\n");
+        assert_eq!(index.line_count(), 2);
+        assert_eq!(index.position(4), position(1, 5, 5));
+        // The end of a text ending with a newline is on the empty line after it.
+        assert_eq!(index.position(5), position(2, 1, 1));
+        assert_eq!(index.position(100), position(2, 1, 1));
+
+        let index = LineIndex::new("a\nbc");
+        assert_eq!(index.position(4), position(2, 3, 3));
+        assert_eq!(index.position(usize::MAX), position(2, 3, 3));
+
+        let index = LineIndex::new("");
+        assert_eq!(index.line_count(), 1);
+        assert_eq!(index.position(0), position(1, 1, 1));
+        assert_eq!(index.position(3), position(1, 1, 1));
+    }
+
+    #[test]
+    fn spans_of_events() {
+        let text = "a\r\n  b \"😀\"\n";
+        let spans: Vec<_> = Parser::new(text)
+            .into_positioned()
+            .map(|event| event.unwrap())
+            .filter(|(event, _)| matches!(event, Event::NodeName { .. } | Event::NodeEntry(_)))
+            .map(|(_, span)| (span.start, span.end))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                (position(1, 1, 1), position(1, 2, 2)),
+                (position(2, 3, 3), position(2, 4, 4)),
+                (position(2, 5, 5), position(2, 8, 9)),
+            ]
+        );
+    }
+}
@@ -1,3 +1,3 @@
+pub(crate) use crate::ParseErrorCause;
 pub(crate) use crate::utils::{Acc, Buffer, OptionExt as _, RangeExt as _};
-pub(crate) use crate::{ParseError, ParseErrorCause, Range, Text};
 pub(crate) use ParseErrorCause::*;
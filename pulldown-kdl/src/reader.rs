@@ -0,0 +1,206 @@
+use std::io::{BufRead, BufReader, Read};
+
+use crate::prelude::*;
+use crate::{Diagnostics, Event, ParseError, ParserOptions, Ranged, StreamParser};
+
+/// An error found while parsing a document from a reader: either the reader failed or the
+/// document is invalid.
+#[derive(Debug)]
+pub enum ReadError {
+    Io(std::io::Error),
+    Parse(ParseError<'static>),
+}
+
+impl From<std::io::Error> for ReadError {
+    fn from(error: std::io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<ParseError<'static>> for ReadError {
+    fn from(error: ParseError<'static>) -> Self {
+        Self::Parse(error)
+    }
+}
+
+impl std::fmt::Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Failed to read the document: {error}"),
+            Self::Parse(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            // The parse error is shown in place of this one, so its source comes next.
+            Self::Parse(error) => error.source(),
+        }
+    }
+}
+
+impl miette::Diagnostic for ReadError {
+    fn code<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        match self {
+            Self::Io(_) => Some(Box::new("pulldown_kdl::io")),
+            Self::Parse(error) => error.code(),
+        }
+    }
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        match self {
+            Self::Io(_) => None,
+            Self::Parse(error) => error.labels(),
+        }
+    }
+    fn help<'a>(&'a self) -> Option<Box<dyn std::fmt::Display + 'a>> {
+        match self {
+            Self::Io(_) => None,
+            Self::Parse(error) => error.help(),
+        }
+    }
+    fn source_code(&self) -> Option<&dyn miette::SourceCode> {
+        match self {
+            Self::Io(_) => None,
+            Self::Parse(error) => error.source_code(),
+        }
+    }
+}
+
+/// A parser that reads the document from a [`BufRead`] as it goes, such as a file or a socket.
+///
+/// Only the part of the document that wasn't parsed yet is kept in memory, so events are always
+/// owned.
+pub struct ReaderParser<R> {
+    reader: R,
+    parser: StreamParser,
+    /// Whether the reader reached its end, so the parser has the whole document.
+    finished: bool,
+}
+
+impl<R: Read> ReaderParser<BufReader<R>> {
+    /// Creates a parser that reads from `reader` through a [`BufReader`].
+    pub fn from_read(reader: R) -> Self {
+        Self::new(BufReader::new(reader))
+    }
+}
+
+impl<R: BufRead> ReaderParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParserOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParserOptions) -> Self {
+        Self {
+            reader,
+            parser: StreamParser::with_options(options),
+            finished: false,
+        }
+    }
+
+    /// Parses the next event, reading more of the document whenever it's needed.
+    pub fn next_event(&mut self) -> Result<Option<Ranged<Event<'static>>>, ReadError> {
+        loop {
+            match self.parser.next_event() {
+                Err(error) if error.cause == NeedsMoreData && !self.finished => self.read()?,
+                event => return Ok(event?),
+            }
+        }
+    }
+
+    /// Feeds the parser whatever the reader has buffered, finishing it once nothing is left.
+    fn read(&mut self) -> std::io::Result<()> {
+        let chunk = loop {
+            match self.reader.fill_buf() {
+                Err(error) if error.kind() == std::io::ErrorKind::Interrupted => continue,
+                chunk => break chunk?,
+            }
+        };
+        if chunk.is_empty() {
+            self.parser.finish();
+            self.finished = true;
+            return Ok(());
+        }
+        let len = chunk.len();
+        self.parser.feed(chunk);
+        self.reader.consume(len);
+        Ok(())
+    }
+
+    /// The errors recovered from so far, if [`ParserOptions::recover`] is enabled.
+    pub fn errors(&self) -> &[ParseError<'static>] {
+        self.parser.errors()
+    }
+
+    /// Takes the errors recovered from so far, so they can be reported together.
+    pub fn into_diagnostics(self) -> Diagnostics<'static> {
+        self.parser.into_diagnostics()
+    }
+}
+
+impl<R: BufRead> Iterator for ReaderParser<R> {
+    type Item = Result<Ranged<Event<'static>>, ReadError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+    use std::io::ErrorKind;
+
+    use super::*;
+    use crate::Parser;
+
+    /// A reader returning each of its reads in turn, and then the end of the text.
+    struct Reads(VecDeque<Result<&'static str, ErrorKind>>);
+
+    impl Read for Reads {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match self.0.pop_front() {
+                Some(Ok(text)) => {
+                    buf[..text.len()].copy_from_slice(text.as_bytes());
+                    Ok(text.len())
+                }
+                Some(Err(kind)) => Err(kind.into()),
+                None => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn retries_interrupted_reads() {
+        let reads = Reads(
+            [
+                Ok("node 1 {\n  ch"),
+                Err(ErrorKind::Interrupted),
+                Ok("ild\n}\n"),
+            ]
+            .into(),
+        );
+        let events: Vec<_> = ReaderParser::from_read(reads)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let expected: Vec<_> = Parser::new("node 1 {\n  child\n}\n")
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn reports_read_errors() {
+        let reads = Reads([Ok("node 1\nother"), Err(ErrorKind::BrokenPipe)].into());
+        let mut parser = ReaderParser::from_read(reads);
+        // The first node was read whole before the error, so it's still parsed.
+        for expected in Parser::new("node 1\n").take(4) {
+            assert_eq!(parser.next_event().unwrap(), Some(expected.unwrap()));
+        }
+        match parser.next_event() {
+            Err(ReadError::Io(error)) => assert_eq!(error.kind(), ErrorKind::BrokenPipe),
+            event => panic!("Expected an I/O error, got {event:?}"),
+        }
+    }
+}
@@ -0,0 +1,1223 @@
+use std::io;
+
+use serde::ser::{self, Impossible, Serialize};
+
+use crate::{
+    Event, KdlNodeEntry, KdlNumber, KdlString, KdlValue, WriteError, Writer, WriterOptions,
+};
+
+/// Serializes `value` as a KDL document, as described in [`Serializer`].
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, SerError> {
+    let mut text = Vec::new();
+    to_writer(&mut text, value)?;
+    Ok(String::from_utf8(text).expect("The serializer only writes UTF-8"))
+}
+
+/// Serializes `value` as a KDL document into `writer`, as described in [`Serializer`].
+pub fn to_writer<W: io::Write, T: Serialize + ?Sized>(
+    writer: W,
+    value: &T,
+) -> Result<(), SerError> {
+    value.serialize(&mut Serializer::new(writer))
+}
+
+/// An error found while serializing a document: either the value can't be written as KDL, or the
+/// writer failed.
+#[derive(Debug)]
+pub enum SerError {
+    Io(io::Error),
+    Custom(String),
+}
+
+impl From<io::Error> for SerError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl std::fmt::Display for SerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "Failed to write the document: {error}"),
+            Self::Custom(message) => message.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for SerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Custom(_) => None,
+        }
+    }
+}
+
+impl ser::Error for SerError {
+    fn custom<T: std::fmt::Display>(message: T) -> Self {
+        Self::Custom(message.to_string())
+    }
+}
+
+/// What a field of a struct or an entry of a map is written as.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FieldKind {
+    /// An argument of the node holding the struct, leaving out the field's name. Only fields of
+    /// structs holding a single value can be arguments, which are read back in the order the
+    /// fields are written.
+    Argument,
+    /// A property of the node holding the struct, as in `name=value`.
+    #[default]
+    Property,
+    /// A child node named after the field.
+    Child,
+}
+
+/// Changes how a [`Serializer`] writes a document.
+#[derive(Clone, Copy, Debug)]
+pub struct SerializerOptions {
+    /// Chooses what each field is written as, given the name of its struct (or of the enum, for
+    /// struct variants, or none for maps) and its own name. Only fields holding a single value can
+    /// be properties, so the others are always child nodes, as are the fields of the root
+    /// document.
+    pub fields: fn(Option<&'static str>, &str) -> FieldKind,
+    /// How nodes, entries and strings are written.
+    pub style: WriterOptions,
+}
+
+impl Default for SerializerOptions {
+    fn default() -> Self {
+        Self {
+            fields: |_, _| FieldKind::Property,
+            style: WriterOptions::default(),
+        }
+    }
+}
+
+/// A [`serde::Serializer`] that writes KDL text through a [`Writer`], mirroring how
+/// [`Deserializer`](crate::Deserializer) reads it back.
+///
+/// The value is written as a document, so it must be a struct, a map or a sequence, whose fields,
+/// entries or items are its nodes. Items of a sequence are nodes called `-`. Below the root:
+/// - Strings, numbers, booleans and `None` are single arguments, so a `port: u16` field is written
+///   as `port 8080`. Strings are quoted as [`SerializerOptions::style`] says, and special floats
+///   and the rest use their `#` keywords.
+/// - Sequences of those are several arguments, and other sequences are children called `-`.
+/// - Structs and maps are written as arguments, properties or child nodes, as chosen by
+///   [`SerializerOptions::fields`].
+/// - Unit enum variants are strings, and other variants are the only child node of their enum,
+///   named after the variant.
+///
+/// Fields written as arguments are only read back when the deserializer is given the same
+/// [`SerializerOptions::fields`], through [`DeserializerOptions`](crate::DeserializerOptions).
+pub struct Serializer<W> {
+    writer: W,
+    options: SerializerOptions,
+}
+
+impl<W: io::Write> Serializer<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_options(writer, SerializerOptions::default())
+    }
+
+    pub fn with_options(writer: W, options: SerializerOptions) -> Self {
+        Self { writer, options }
+    }
+
+    /// Gives back the writer the document was written to.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn value(&self) -> ValueSerializer<'_> {
+        ValueSerializer {
+            options: &self.options,
+            root: true,
+        }
+    }
+
+    /// Starts serializing a compound value as the root document, which is written once it ends.
+    fn document<'a, C>(
+        &'a mut self,
+        start: impl FnOnce(ValueSerializer<'a>) -> Result<C, SerError>,
+    ) -> Result<Document<'a, W, C>, SerError> {
+        let value = ValueSerializer {
+            options: &self.options,
+            root: true,
+        };
+        Ok(Document {
+            inner: start(value)?,
+            writer: &mut self.writer,
+            style: self.options.style,
+        })
+    }
+
+    fn write(&mut self, document: Content) -> Result<(), SerError> {
+        write_document(&mut self.writer, self.options.style, document)
+    }
+}
+
+/// Writes the nodes of the root document.
+fn write_document(
+    mut writer: impl io::Write,
+    style: WriterOptions,
+    document: Content,
+) -> Result<(), SerError> {
+    if !document.arguments.is_empty() || !document.properties.is_empty() {
+        return Err(ser::Error::custom(
+            "Only structs, maps and sequences can be written as documents",
+        ));
+    }
+    let mut text = Writer::with_options(String::new(), style);
+    write_children(&mut text, document.children).expect("Nodes are written in order");
+    let text = text
+        .finish()
+        .expect("Every node and children block written ends");
+    writer.write_all(text.as_bytes())?;
+    Ok(())
+}
+
+/// Forwards serializing a value to the [`ValueSerializer`] of the root document, writing the
+/// resulting nodes.
+macro_rules! serialize_root {
+    ($($method:ident($($arg:ident: $ty:ty),*);)*) => {
+        $(fn $method(self, $($arg: $ty),*) -> Result<(), SerError> {
+            let document = self.value().$method($($arg),*)?;
+            self.write(document)
+        })*
+    };
+}
+
+impl<'a, W: io::Write> ser::Serializer for &'a mut Serializer<W> {
+    type Ok = ();
+    type Error = SerError;
+    type SerializeSeq = SeqSerializer<'a, W>;
+    type SerializeTuple = SeqSerializer<'a, W>;
+    type SerializeTupleStruct = SeqSerializer<'a, W>;
+    type SerializeTupleVariant = SeqSerializer<'a, W>;
+    type SerializeMap = FieldsSerializer<'a, W>;
+    type SerializeStruct = FieldsSerializer<'a, W>;
+    type SerializeStructVariant = FieldsSerializer<'a, W>;
+
+    serialize_root! {
+        serialize_bool(v: bool);
+        serialize_i8(v: i8);
+        serialize_i16(v: i16);
+        serialize_i32(v: i32);
+        serialize_i64(v: i64);
+        serialize_i128(v: i128);
+        serialize_u8(v: u8);
+        serialize_u16(v: u16);
+        serialize_u32(v: u32);
+        serialize_u64(v: u64);
+        serialize_u128(v: u128);
+        serialize_f32(v: f32);
+        serialize_f64(v: f64);
+        serialize_char(v: char);
+        serialize_str(v: &str);
+        serialize_bytes(v: &[u8]);
+        serialize_none();
+        serialize_unit();
+        serialize_unit_struct(name: &'static str);
+        serialize_unit_variant(name: &'static str, index: u32, variant: &'static str);
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<(), SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        let document = self
+            .value()
+            .serialize_newtype_variant(name, index, variant, value)?;
+        self.write(document)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, SerError> {
+        self.document(|value| value.serialize_seq(len))
+            .map(SeqSerializer)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, SerError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, SerError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerError> {
+        self.document(|value| value.serialize_tuple_variant(name, index, variant, len))
+            .map(SeqSerializer)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, SerError> {
+        self.document(|value| value.serialize_map(len))
+            .map(FieldsSerializer)
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, SerError> {
+        self.document(|value| value.serialize_struct(name, len))
+            .map(FieldsSerializer)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerError> {
+        self.document(|value| value.serialize_struct_variant(name, index, variant, len))
+            .map(FieldsSerializer)
+    }
+}
+
+/// What a value is written as: the arguments, properties and children of the node holding it.
+#[derive(Default)]
+pub(crate) struct Content {
+    arguments: Vec<KdlValue<'static>>,
+    properties: Vec<(String, KdlValue<'static>)>,
+    children: Vec<(String, Content)>,
+    /// Whether the content is a sequence, struct or map, which is never a single value even if it
+    /// holds a single argument, so it's read back as the same type.
+    compound: bool,
+}
+
+impl Content {
+    fn argument(value: KdlValue<'static>) -> Self {
+        Self {
+            arguments: vec![value],
+            ..Default::default()
+        }
+    }
+
+    /// The content of a number, written as `lexeme`.
+    fn number(lexeme: impl std::fmt::Display) -> Self {
+        let lexeme = lexeme.to_string();
+        let number = KdlNumber::parse(&lexeme).expect("Rust numbers are valid KDL numbers");
+        Self::argument(KdlValue::Num(number.into_owned()))
+    }
+
+    fn string(value: &str) -> Self {
+        Self::argument(KdlValue::String(string(value.to_owned())))
+    }
+
+    fn float(value: f64, text: impl std::fmt::Debug) -> Self {
+        match value {
+            _ if value.is_nan() => Self::number("#nan"),
+            f64::INFINITY => Self::number("#inf"),
+            f64::NEG_INFINITY => Self::number("#-inf"),
+            _ => Self::number(format!("{text:?}")),
+        }
+    }
+
+    /// The content of an enum whose variant holds `content`.
+    fn variant(variant: &'static str, content: Content) -> Self {
+        Self {
+            children: vec![(variant.to_owned(), content)],
+            ..Default::default()
+        }
+    }
+
+    /// The value of the content, if it's a single argument that isn't a sequence, struct or map.
+    fn single_value(&self) -> Option<&KdlValue<'static>> {
+        match &self.arguments[..] {
+            [value] if !self.compound && self.properties.is_empty() && self.children.is_empty() => {
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Writes the content as a node called `name`.
+    fn write(self, writer: &mut Writer<String>, name: String) -> Result<(), WriteError> {
+        writer.write_event(Event::NodeName {
+            ty: None,
+            name: string(name),
+        })?;
+        for value in self.arguments {
+            writer.write_event(Event::NodeEntry(KdlNodeEntry::Argument { ty: None, value }))?;
+        }
+        for (key, value) in self.properties {
+            writer.write_event(Event::NodeEntry(KdlNodeEntry::Property {
+                key: string(key),
+                ty: None,
+                value,
+            }))?;
+        }
+        if !self.children.is_empty() {
+            write_children(writer, self.children)?;
+        }
+        writer.write_event(Event::NodeEnd { inline: false })
+    }
+}
+
+/// Writes a document, either the root one or a children block, made of `children`.
+fn write_children(
+    writer: &mut Writer<String>,
+    children: Vec<(String, Content)>,
+) -> Result<(), WriteError> {
+    writer.write_event(Event::StartDocument)?;
+    for (name, child) in children {
+        child.write(writer, name)?;
+    }
+    writer.write_event(Event::EndDocument)
+}
+
+fn string(string: String) -> KdlString<'static> {
+    KdlString {
+        string: string.into(),
+    }
+}
+
+/// Serializes a value into the [`Content`] of the node holding it.
+#[derive(Clone, Copy)]
+struct ValueSerializer<'o> {
+    options: &'o SerializerOptions,
+    /// Whether the value is the root document, whose fields and items are always nodes.
+    root: bool,
+}
+
+impl<'o> ser::Serializer for ValueSerializer<'o> {
+    type Ok = Content;
+    type Error = SerError;
+    type SerializeSeq = Seq<'o>;
+    type SerializeTuple = Seq<'o>;
+    type SerializeTupleStruct = Seq<'o>;
+    type SerializeTupleVariant = Seq<'o>;
+    type SerializeMap = Fields<'o>;
+    type SerializeStruct = Fields<'o>;
+    type SerializeStructVariant = Fields<'o>;
+
+    fn serialize_bool(self, v: bool) -> Result<Content, SerError> {
+        Ok(Content::argument(KdlValue::Bool(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Content, SerError> {
+        Ok(Content::number(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Content, SerError> {
+        Ok(Content::number(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Content, SerError> {
+        Ok(Content::number(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Content, SerError> {
+        Ok(Content::number(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Content, SerError> {
+        Ok(Content::number(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Content, SerError> {
+        Ok(Content::number(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Content, SerError> {
+        Ok(Content::number(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Content, SerError> {
+        Ok(Content::number(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Content, SerError> {
+        Ok(Content::number(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Content, SerError> {
+        Ok(Content::number(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Content, SerError> {
+        Ok(Content::float(v.into(), v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Content, SerError> {
+        Ok(Content::float(v, v))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Content, SerError> {
+        Ok(Content::string(v.encode_utf8(&mut [0; 4])))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Content, SerError> {
+        Ok(Content::string(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Content, SerError> {
+        v.serialize(self)
+    }
+
+    fn serialize_none(self) -> Result<Content, SerError> {
+        Ok(Content::argument(KdlValue::Null))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Content, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Content, SerError> {
+        Ok(Content::default())
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Content, SerError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Content, SerError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Content, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Content, SerError> {
+        let content = value.serialize(ValueSerializer {
+            root: false,
+            ..self
+        })?;
+        Ok(Content::variant(variant, content))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Seq<'o>, SerError> {
+        Ok(Seq {
+            serializer: ValueSerializer {
+                root: false,
+                ..self
+            },
+            root: self.root,
+            variant: None,
+            items: Vec::new(),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Seq<'o>, SerError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, len: usize) -> Result<Seq<'o>, SerError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Seq<'o>, SerError> {
+        let seq = ValueSerializer {
+            root: false,
+            ..self
+        }
+        .serialize_seq(Some(len))?;
+        Ok(Seq {
+            variant: Some(variant),
+            ..seq
+        })
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Fields<'o>, SerError> {
+        Ok(Fields {
+            serializer: ValueSerializer {
+                root: false,
+                ..self
+            },
+            root: self.root,
+            name: None,
+            variant: None,
+            key: None,
+            content: Content {
+                compound: true,
+                ..Default::default()
+            },
+        })
+    }
+
+    fn serialize_struct(self, name: &'static str, _: usize) -> Result<Fields<'o>, SerError> {
+        Ok(Fields {
+            name: Some(name),
+            ..self.serialize_map(None)?
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Fields<'o>, SerError> {
+        let fields = ValueSerializer {
+            root: false,
+            ..self
+        }
+        .serialize_struct(name, len)?;
+        Ok(Fields {
+            variant: Some(variant),
+            ..fields
+        })
+    }
+}
+
+/// The items of a sequence, which become arguments if they are all single values, or else
+/// children called `-`.
+pub(crate) struct Seq<'o> {
+    serializer: ValueSerializer<'o>,
+    /// Whether the sequence is the root document, whose items are always nodes.
+    root: bool,
+    /// The enum variant holding the sequence, if any.
+    variant: Option<&'static str>,
+    items: Vec<Content>,
+}
+
+impl Seq<'_> {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        self.items.push(value.serialize(self.serializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Content, SerError> {
+        let arguments = match self.root {
+            true => None,
+            false => self
+                .items
+                .iter()
+                .map(|item| item.single_value().cloned())
+                .collect(),
+        };
+        let content = match arguments {
+            Some(arguments) => Content {
+                arguments,
+                compound: true,
+                ..Default::default()
+            },
+            None => Content {
+                children: self
+                    .items
+                    .into_iter()
+                    .map(|item| ("-".into(), item))
+                    .collect(),
+                compound: true,
+                ..Default::default()
+            },
+        };
+        Ok(match self.variant {
+            Some(variant) => Content::variant(variant, content),
+            None => content,
+        })
+    }
+}
+
+/// Implements the traits of sequences for [`Seq`], which are all the same.
+macro_rules! impl_seq {
+    ($($trait:ident::$method:ident),*) => {
+        $(impl ser::$trait for Seq<'_> {
+            type Ok = Content;
+            type Error = SerError;
+
+            fn $method<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+                self.push(value)
+            }
+
+            fn end(self) -> Result<Content, SerError> {
+                Seq::end(self)
+            }
+        })*
+    };
+}
+
+impl_seq!(
+    SerializeSeq::serialize_element,
+    SerializeTuple::serialize_element,
+    SerializeTupleStruct::serialize_field,
+    SerializeTupleVariant::serialize_field
+);
+
+/// The fields of a struct or the entries of a map, placed as chosen by
+/// [`SerializerOptions::fields`].
+pub(crate) struct Fields<'o> {
+    serializer: ValueSerializer<'o>,
+    /// Whether the fields are the root document, where they are always nodes.
+    root: bool,
+    /// The name of the struct, or none for maps.
+    name: Option<&'static str>,
+    /// The enum variant holding the struct, if any.
+    variant: Option<&'static str>,
+    /// The key of the map entry whose value is next.
+    key: Option<String>,
+    content: Content,
+}
+
+impl Fields<'_> {
+    fn push<T: Serialize + ?Sized>(&mut self, key: &str, value: &T) -> Result<(), SerError> {
+        let value = value.serialize(self.serializer)?;
+        let kind = match self.root {
+            true => FieldKind::Child,
+            false => (self.serializer.options.fields)(self.name, key),
+        };
+        match (kind, value.single_value()) {
+            // Arguments are told apart by their position, so none may be left out or moved.
+            (FieldKind::Argument, _) if self.name.is_none() => {
+                return Err(ser::Error::custom(format!(
+                    "The map entry `{key}` can't be an argument, only struct fields can"
+                )));
+            }
+            (FieldKind::Argument, Some(value)) => self.content.arguments.push(value.clone()),
+            (FieldKind::Argument, None) => {
+                return Err(ser::Error::custom(format!(
+                    "The field `{key}` is an argument, so it must hold a single value"
+                )));
+            }
+            (FieldKind::Property, Some(value)) => {
+                self.content.properties.retain(|(k, _)| k != key);
+                self.content
+                    .properties
+                    .push((key.to_owned(), value.clone()));
+            }
+            _ => self.content.children.push((key.to_owned(), value)),
+        }
+        Ok(())
+    }
+
+    fn end(self) -> Result<Content, SerError> {
+        Ok(match self.variant {
+            Some(variant) => Content::variant(variant, self.content),
+            None => self.content,
+        })
+    }
+}
+
+impl ser::SerializeMap for Fields<'_> {
+    type Ok = Content;
+    type Error = SerError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerError> {
+        self.key = Some(key.serialize(KeySerializer)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        let key = self
+            .key
+            .take()
+            .expect("A value is serialized after its key");
+        self.push(&key, value)
+    }
+
+    fn end(self) -> Result<Content, SerError> {
+        Fields::end(self)
+    }
+}
+
+impl ser::SerializeStruct for Fields<'_> {
+    type Ok = Content;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Content, SerError> {
+        Fields::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for Fields<'_> {
+    type Ok = Content;
+    type Error = SerError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerError> {
+        self.push(key, value)
+    }
+
+    fn end(self) -> Result<Content, SerError> {
+        Fields::end(self)
+    }
+}
+
+/// A sequence, struct or map that is the root document, written once it ends.
+struct Document<'a, W, C> {
+    writer: &'a mut W,
+    style: WriterOptions,
+    inner: C,
+}
+
+/// Serializes a sequence, tuple or tuple variant as the root document, whose items are nodes
+/// called `-`.
+pub struct SeqSerializer<'a, W>(Document<'a, W, Seq<'a>>);
+
+/// Serializes a struct, map or struct variant as the root document, whose fields or entries are
+/// nodes.
+pub struct FieldsSerializer<'a, W>(Document<'a, W, Fields<'a>>);
+
+/// Implements the traits of compound values for the root document, forwarding them to the
+/// [`ValueSerializer`]'s and writing the resulting nodes once they end.
+macro_rules! impl_document {
+    ($($ty:ident: $trait:ident::$method:ident($($key:ident: $key_ty:ty),*)),*) => {
+        $(impl<W: io::Write> ser::$trait for $ty<'_, W> {
+            type Ok = ();
+            type Error = SerError;
+
+            fn $method<T: Serialize + ?Sized>(
+                &mut self,
+                $($key: $key_ty,)*
+                value: &T,
+            ) -> Result<(), SerError> {
+                ser::$trait::$method(&mut self.0.inner, $($key,)* value)
+            }
+
+            fn end(self) -> Result<(), SerError> {
+                let document = ser::$trait::end(self.0.inner)?;
+                write_document(self.0.writer, self.0.style, document)
+            }
+        })*
+    };
+}
+
+impl_document!(
+    SeqSerializer: SerializeSeq::serialize_element(),
+    SeqSerializer: SerializeTuple::serialize_element(),
+    SeqSerializer: SerializeTupleStruct::serialize_field(),
+    SeqSerializer: SerializeTupleVariant::serialize_field(),
+    FieldsSerializer: SerializeStruct::serialize_field(key: &'static str),
+    FieldsSerializer: SerializeStructVariant::serialize_field(key: &'static str)
+);
+
+impl<W: io::Write> ser::SerializeMap for FieldsSerializer<'_, W> {
+    type Ok = ();
+    type Error = SerError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerError> {
+        ser::SerializeMap::serialize_key(&mut self.0.inner, key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerError> {
+        ser::SerializeMap::serialize_value(&mut self.0.inner, value)
+    }
+
+    fn end(self) -> Result<(), SerError> {
+        let document = ser::SerializeMap::end(self.0.inner)?;
+        write_document(self.0.writer, self.0.style, document)
+    }
+}
+
+/// Serializes the key of a map entry, which must be a string, a character or an integer.
+struct KeySerializer;
+
+impl KeySerializer {
+    fn invalid() -> SerError {
+        ser::Error::custom("Map keys must be strings, characters or integers")
+    }
+}
+
+/// Writes keys that are integers as their decimal representation.
+macro_rules! serialize_integer_key {
+    ($($method:ident($ty:ty)),*) => {
+        $(fn $method(self, v: $ty) -> Result<String, SerError> {
+            Ok(v.to_string())
+        })*
+    };
+}
+
+/// Rejects keys that aren't strings, characters or integers.
+macro_rules! reject_key {
+    ($($method:ident($($arg:ident: $ty:ty),*) -> $ok:ty;)*) => {
+        $(fn $method(self, $(_: $ty),*) -> Result<$ok, SerError> {
+            Err(Self::invalid())
+        })*
+    };
+}
+
+impl ser::Serializer for KeySerializer {
+    type Ok = String;
+    type Error = SerError;
+    type SerializeSeq = Impossible<String, SerError>;
+    type SerializeTuple = Impossible<String, SerError>;
+    type SerializeTupleStruct = Impossible<String, SerError>;
+    type SerializeTupleVariant = Impossible<String, SerError>;
+    type SerializeMap = Impossible<String, SerError>;
+    type SerializeStruct = Impossible<String, SerError>;
+    type SerializeStructVariant = Impossible<String, SerError>;
+
+    serialize_integer_key!(
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128)
+    );
+
+    fn serialize_char(self, v: char) -> Result<String, SerError> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, SerError> {
+        Ok(v.to_owned())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<String, SerError> {
+        Ok(variant.to_owned())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<String, SerError> {
+        value.serialize(self)
+    }
+
+    reject_key! {
+        serialize_bool(v: bool) -> String;
+        serialize_f32(v: f32) -> String;
+        serialize_f64(v: f64) -> String;
+        serialize_bytes(v: &[u8]) -> String;
+        serialize_none() -> String;
+        serialize_unit() -> String;
+        serialize_unit_struct(name: &'static str) -> String;
+        serialize_seq(len: Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(len: usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(name: &'static str, len: usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(
+            name: &'static str,
+            index: u32,
+            variant: &'static str,
+            len: usize
+        ) -> Self::SerializeTupleVariant;
+        serialize_map(len: Option<usize>) -> Self::SerializeMap;
+        serialize_struct(name: &'static str, len: usize) -> Self::SerializeStruct;
+        serialize_struct_variant(
+            name: &'static str,
+            index: u32,
+            variant: &'static str,
+            len: usize
+        ) -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _: &T) -> Result<String, SerError> {
+        Err(Self::invalid())
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<String, SerError> {
+        Err(Self::invalid())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+    use crate::{Deserializer, DeserializerOptions, Indent, Quotes, from_str};
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    enum Shape {
+        Empty,
+        Circle(f64),
+        Point(i32, i32),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Server {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Config {
+        name: String,
+        strings: Vec<String>,
+        letter: char,
+        big: u64,
+        small: i128,
+        ratio: f32,
+        far: f64,
+        debug: bool,
+        missing: Option<u8>,
+        present: Option<u8>,
+        unit: (),
+        server: Server,
+        servers: Vec<Server>,
+        limits: BTreeMap<String, u32>,
+        one: Vec<i32>,
+        none: Vec<i32>,
+        nested: Vec<Vec<u8>>,
+        matrix: Vec<Vec<u8>>,
+        shapes: Vec<Shape>,
+    }
+
+    /// Serializes `value` and checks that it's deserialized back as it was.
+    fn round_trip<T>(value: &T) -> String
+    where
+        T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
+    {
+        let text = to_string(value).unwrap();
+        let read: T = from_str(&text).unwrap_or_else(|error| panic!("{error}\n{text}"));
+        assert_eq!(&read, value, "{text}");
+        text
+    }
+
+    #[test]
+    fn writes_documents() {
+        let server = Server {
+            host: "example.com".into(),
+            port: 80,
+        };
+        assert_eq!(round_trip(&server), "host example.com\nport 80\n");
+
+        let servers = vec![server];
+        let text = round_trip(&(servers, Shape::Rect { w: 1, h: 2 }));
+        assert_eq!(
+            text,
+            "- {\n    - host=example.com port=80\n}\n- {\n    Rect w=1 h=2\n}\n"
+        );
+
+        let options = SerializerOptions {
+            style: WriterOptions {
+                indent: Indent::Tabs,
+                quotes: Quotes::Values,
+            },
+            ..Default::default()
+        };
+        let mut serializer = Serializer::with_options(Vec::new(), options);
+        let server = Server {
+            host: "local".into(),
+            port: 1,
+        };
+        BTreeMap::from([("servers", vec![server])])
+            .serialize(&mut serializer)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(serializer.into_inner()).unwrap(),
+            "servers {\n\t- host=\"local\" port=1\n}\n"
+        );
+    }
+
+    #[test]
+    fn round_trips() {
+        round_trip(&Config {
+            name: "my app".into(),
+            strings: vec![
+                "plain".into(),
+                "true".into(),
+                "1st".into(),
+                "-.5".into(),
+                "".into(),
+                "say \"hi\" \\o/".into(),
+                "two\nlines\t\u{7}".into(),
+                "#null".into(),
+            ],
+            letter: '=',
+            big: u64::MAX,
+            small: i128::MIN,
+            ratio: 0.1,
+            far: f64::NEG_INFINITY,
+            debug: false,
+            missing: None,
+            present: Some(0),
+            unit: (),
+            server: Server {
+                host: "a b".into(),
+                port: 8080,
+            },
+            servers: vec![],
+            limits: BTreeMap::from([("a".into(), 1), ("b c".into(), 2)]),
+            one: vec![1],
+            none: vec![],
+            nested: vec![vec![2]],
+            matrix: vec![vec![1, 2], vec![], vec![3]],
+            shapes: vec![
+                Shape::Empty,
+                Shape::Circle(1.5),
+                Shape::Point(-1, 2),
+                Shape::Rect { w: 3, h: 4 },
+            ],
+        });
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    enum Item {
+        Sword {
+            name: String,
+            damage: u32,
+            cursed: bool,
+        },
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Point {
+        x: i32,
+        label: Option<String>,
+        y: i32,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Scene {
+        points: Vec<Point>,
+        items: Vec<Item>,
+    }
+
+    /// Writes the coordinates of points and the name and damage of items as arguments.
+    fn fields(name: Option<&'static str>, field: &str) -> FieldKind {
+        match (name, field) {
+            (Some("Point"), "x" | "y") | (Some("Item"), "name" | "damage") => FieldKind::Argument,
+            (Some("Item"), _) => FieldKind::Child,
+            _ => FieldKind::Property,
+        }
+    }
+
+    #[test]
+    fn round_trips_arguments() {
+        let scene = Scene {
+            points: vec![
+                Point {
+                    x: 1,
+                    label: None,
+                    y: -2,
+                },
+                Point {
+                    x: 3,
+                    label: Some("origin".into()),
+                    y: 4,
+                },
+            ],
+            items: vec![Item::Sword {
+                name: "Excalibur".into(),
+                damage: 10,
+                cursed: false,
+            }],
+        };
+        let mut serializer = Serializer::with_options(
+            Vec::new(),
+            SerializerOptions {
+                fields,
+                ..Default::default()
+            },
+        );
+        scene.serialize(&mut serializer).unwrap();
+        let text = String::from_utf8(serializer.into_inner()).unwrap();
+        assert_eq!(
+            text,
+            "points {\n    - 1 -2 label=#null\n    - 3 4 label=origin\n}\nitems {\n    - {\n        Sword Excalibur 10 {\n            cursed #false\n        }\n    }\n}\n"
+        );
+
+        let mut deserializer = Deserializer::with_options(&text, DeserializerOptions { fields });
+        assert_eq!(Scene::deserialize(&mut deserializer).unwrap(), scene);
+
+        // Without the same options, arguments can't be told apart.
+        assert!(from_str::<Scene>(&text).is_err());
+
+        let text = "points {\n    - 1 2 3\n}\nitems {\n}\n";
+        let mut deserializer = Deserializer::with_options(text, DeserializerOptions { fields });
+        let error = Scene::deserialize(&mut deserializer).unwrap_err();
+        assert!(error.to_string().contains("more arguments"), "{error}");
+    }
+
+    #[derive(Serialize)]
+    struct Ports {
+        ports: Vec<u16>,
+    }
+
+    #[test]
+    fn rejects_what_cant_be_written() {
+        let error = |result: Result<String, SerError>| match result {
+            Err(SerError::Custom(message)) => message,
+            result => panic!("Expected an error, got {result:?}"),
+        };
+        assert!(error(to_string(&1)).contains("as documents"));
+        assert!(error(to_string(&BTreeMap::from([((), 1)]))).contains("Map keys"));
+
+        let argument = |_: Option<&'static str>, _: &str| FieldKind::Argument;
+        let options = SerializerOptions {
+            fields: argument,
+            ..Default::default()
+        };
+        let mut serializer = Serializer::with_options(Vec::new(), options);
+        let map = BTreeMap::from([("key", BTreeMap::from([("a", 1)]))]);
+        let message = error(map.serialize(&mut serializer).map(|()| String::new()));
+        assert!(message.contains("map entry `a`"), "{message}");
+
+        let mut serializer = Serializer::with_options(Vec::new(), options);
+        let value = BTreeMap::from([("key", Ports { ports: vec![1, 2] })]);
+        let message = error(value.serialize(&mut serializer).map(|()| String::new()));
+        assert!(message.contains("field `ports`"), "{message}");
+    }
+}
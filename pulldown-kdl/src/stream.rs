@@ -0,0 +1,226 @@
+use crate::position::LineIndex;
+use crate::prelude::*;
+use crate::string::is_newline;
+use crate::{Diagnostics, Event, ParseError, Parser, ParserOptions, Ranged};
+
+/// A parser for documents that arrive in chunks, such as from a file or a socket, which only keeps
+/// around the text it hasn't parsed yet.
+///
+/// Chunks are given to it with [`StreamParser::feed`], and [`StreamParser::finish`] tells it that
+/// the document is over. Until then, [`StreamParser::next_event`] returns a
+/// [`ParseErrorCause::NeedsMoreData`] error whenever the text fed so far isn't enough to know what
+/// the next event is. That error isn't fatal: it just means more text must be fed before asking
+/// again. Once finished, though, it means the document ended abruptly.
+///
+/// Since the text is discarded as it's parsed, events are always owned. Their ranges are still
+/// relative to the start of the whole document.
+///
+/// Until the document is finished, only whole lines are parsed, since a token at the end of the
+/// text fed so far may go on in the next chunk. This has a few costs to keep in mind:
+/// - A document without newlines, such as a minified one, is kept whole in memory and only parsed
+///   once finished.
+/// - A token spanning many lines, such as a long multi-line string or comment, is parsed again from
+///   its start every time more of its lines are fed, which takes quadratic time in its length.
+/// - Every call to [`StreamParser::next_event`] copies the state of the parser, so it can be kept
+///   as it was if more text is needed.
+#[derive(Clone, Default)]
+pub struct StreamParser {
+    /// The state of the parser, without the text it parses.
+    parser: Parser<'static>,
+    /// The text fed and not discarded yet, which starts at the parser's origin.
+    text: String,
+    /// How much of `text` was already parsed.
+    parsed: usize,
+    /// The start of a character split between chunks.
+    incomplete: Vec<u8>,
+    /// Where the fed bytes stopped being valid UTF-8, past which nothing can be parsed.
+    invalid_utf8: Option<usize>,
+    finished: bool,
+    /// The errors recovered from so far.
+    errors: Vec<ParseError<'static>>,
+}
+
+impl StreamParser {
+    pub fn new() -> Self {
+        Self::with_options(ParserOptions::default())
+    }
+
+    pub fn with_options(options: ParserOptions) -> Self {
+        Self {
+            parser: Parser {
+                options,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Adds the next chunk of the document, which may end anywhere, even in the middle of a
+    /// character.
+    pub fn feed(&mut self, chunk: &[u8]) {
+        self.discard_parsed();
+        if self.invalid_utf8.is_some() {
+            return;
+        }
+        self.incomplete.extend_from_slice(chunk);
+        let valid = match std::str::from_utf8(&self.incomplete) {
+            Ok(text) => text.len(),
+            Err(error) => {
+                // Sequences cut short by the end of the chunk may still be completed by the next.
+                if error.error_len().is_some() {
+                    let at = self.parser.origin.offset + self.text.len() + error.valid_up_to();
+                    self.invalid_utf8 = Some(at);
+                }
+                error.valid_up_to()
+            }
+        };
+        let text = std::str::from_utf8(&self.incomplete[..valid])
+            .expect("The bytes up to the error should be valid UTF-8");
+        self.text.push_str(text);
+        self.incomplete.drain(..valid);
+    }
+
+    /// Tells the parser that the whole document was fed, so it parses what's left of it.
+    pub fn finish(&mut self) {
+        if !self.incomplete.is_empty() && self.invalid_utf8.is_none() {
+            self.invalid_utf8 = Some(self.parser.origin.offset + self.text.len());
+        }
+        self.finished = true;
+    }
+
+    pub fn next_event(&mut self) -> Result<Option<Ranged<Event<'static>>>, ParseError<'static>> {
+        // Nothing after invalid UTF-8 can be parsed, so the document never really ends.
+        let partial = !self.finished || self.invalid_utf8.is_some();
+        // Only multi-line strings and comments may span lines, and those report running out of
+        // text, so whole lines are parsed until the document is over.
+        let end = if partial && self.invalid_utf8.is_none() {
+            lines_end(&self.text)
+        } else {
+            self.text.len()
+        };
+        let mut parser: Parser = self.parser.clone();
+        parser.acc = Acc {
+            base: &self.text[..end],
+            end: self.parsed,
+        };
+        parser.partial = partial;
+        parser.truncated = self
+            .invalid_utf8
+            .map(|at| InvalidUtf8.at(at - self.parser.origin.offset));
+
+        let event = match parser.next_event_borrowed() {
+            // The saved state is kept, so the same event is parsed again with more text.
+            Err(error) if partial && matches!(error.cause, NeedsMoreData | InvalidUtf8) => {
+                return Err(error.into_owned());
+            }
+            event => event,
+        };
+
+        self.parsed = parser.acc.end;
+        self.errors
+            .extend(parser.errors.drain(..).map(ParseError::into_owned));
+        self.parser = Parser {
+            acc: Acc::default(),
+            document_depth: parser.document_depth,
+            state: parser.state,
+            options: parser.options,
+            slashdashes: parser.slashdashes,
+            slashdash_ended: parser.slashdash_ended,
+            errors: Vec::new(),
+            pending: parser
+                .pending
+                .map(|(event, range)| (event.into_owned(), range)),
+            ended_abruptly: parser.ended_abruptly,
+            node_name: parser.node_name.into_owned(),
+            open_blocks: parser
+                .open_blocks
+                .into_iter()
+                .map(|(node, at)| (node.into_owned(), at))
+                .collect(),
+            origin: parser.origin,
+            partial: false,
+            truncated: None,
+        };
+        event
+            .map(|event| event.map(|(event, range)| (event.into_owned(), range)))
+            .map_err(ParseError::into_owned)
+    }
+
+    /// The errors recovered from so far, if [`ParserOptions::recover`] is enabled.
+    pub fn errors(&self) -> &[ParseError<'static>] {
+        &self.errors
+    }
+
+    /// Takes the errors recovered from so far, so they can be reported together.
+    pub fn into_diagnostics(self) -> Diagnostics<'static> {
+        self.errors.into_iter().collect()
+    }
+
+    /// Discards the lines that were already parsed, keeping the text the parser still needs.
+    fn discard_parsed(&mut self) {
+        let end = lines_end(&self.text[..self.parsed]);
+        // A `\r` followed by a `\n` is a single line break, so it can't be split. A pending event's
+        // range is also relative to the text it was found in.
+        let split_line_break = self.text[..end].ends_with('\r')
+            && (end == self.text.len() || self.text[end..].starts_with('\n'));
+        if end == 0 || split_line_break || self.parser.pending.is_some() {
+            return;
+        }
+        self.parser.origin.line += LineIndex::new(&self.text[..end]).line_count() - 1;
+        self.parser.origin.offset += end;
+        self.text.drain(..end);
+        self.parsed -= end;
+    }
+}
+
+/// Where the last whole line of `text` ends, or 0 if it has none.
+fn lines_end(text: &str) -> usize {
+    text.char_indices()
+        .rfind(|&(_, c)| is_newline(c))
+        .map_or(0, |(i, c)| i + c.len_utf8())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Feeds `chunks` one at a time, taking every event each of them allows, and returns how many
+    /// events there were and the most text that was kept at once.
+    fn feed_all<'a>(chunks: impl IntoIterator<Item = &'a str>) -> (usize, usize) {
+        let mut parser = StreamParser::new();
+        let mut events = 0;
+        let mut buffered = 0;
+        let mut chunks = chunks.into_iter();
+        loop {
+            match parser.next_event() {
+                Ok(Some(_)) => events += 1,
+                Ok(None) => return (events, buffered),
+                Err(error) if error.cause == NeedsMoreData && !parser.finished => {
+                    match chunks.next() {
+                        Some(chunk) => parser.feed(chunk.as_bytes()),
+                        None => parser.finish(),
+                    }
+                    buffered = buffered.max(parser.text.len());
+                }
+                Err(error) => panic!("{error}"),
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_only_unparsed_lines() {
+        let line = "node 1 key=\"value\" {\n    child #true\n}\n";
+        let (events, buffered) = feed_all(std::iter::repeat_n(line, 1000));
+        assert!(events > 1000);
+        // At most the line being parsed is kept, plus the chunk that completed it.
+        assert!(buffered <= 2 * line.len(), "{buffered} bytes were kept");
+    }
+
+    #[test]
+    fn keeps_documents_without_newlines_whole() {
+        let chunk = "node 1; ";
+        let (events, buffered) = feed_all(std::iter::repeat_n(chunk, 100));
+        assert!(events > 100);
+        assert_eq!(buffered, 100 * chunk.len());
+    }
+}
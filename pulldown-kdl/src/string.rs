@@ -1,55 +1,102 @@
 use std::borrow::Cow;
 
-use crate::prelude::*;
 use crate::ParseResult;
 use crate::Ranged;
-use crate::{item, Item, Text};
+use crate::comment::ParseComment;
+use crate::error::{self, Located};
+use crate::number::is_sign;
+use crate::prelude::*;
+use crate::{Item, Text, item};
 
 pub(crate) const fn is_digit(c: char) -> bool {
-    match c {
-        '0'..='9' => true,
-        _ => false,
-    }
+    c.is_ascii_digit()
 }
 
 pub(crate) const fn is_whitespace(c: char) -> bool {
-    match c {
-        '\u{0009}' | '\u{000B}' | '\u{0020}' | '\u{00A0}' | '\u{1680}' | '\u{2000}'
-        | '\u{2001}' | '\u{2002}' | '\u{2003}' | '\u{2004}' | '\u{2005}' | '\u{2006}'
-        | '\u{2007}' | '\u{2008}' | '\u{2009}' | '\u{200A}' | '\u{202F}' | '\u{205F}'
-        | '\u{3000}' => true,
-        _ => false,
-    }
+    matches!(
+        c,
+        '\u{0009}'
+            | '\u{000B}'
+            | '\u{0020}'
+            | '\u{00A0}'
+            | '\u{1680}'
+            | '\u{2000}'
+            | '\u{2001}'
+            | '\u{2002}'
+            | '\u{2003}'
+            | '\u{2004}'
+            | '\u{2005}'
+            | '\u{2006}'
+            | '\u{2007}'
+            | '\u{2008}'
+            | '\u{2009}'
+            | '\u{200A}'
+            | '\u{202F}'
+            | '\u{205F}'
+            | '\u{3000}'
+    )
 }
 
 pub(crate) const fn is_equals(c: char) -> bool {
-    match c {
-        '=' | '﹦' | '＝' | '🟰' => true,
-        _ => false,
-    }
+    matches!(c, '=' | '﹦' | '＝' | '🟰')
 }
 
 pub(crate) const fn is_newline(c: char) -> bool {
-    match c {
-        '\r' | '\n' => true,
-        '\u{0085}' => true,
-        '\u{000C}' => true,
-        '\u{2028}' => true,
-        '\u{2029}' => true,
-        _ => false,
+    matches!(
+        c,
+        '\r' | '\n' | '\u{0085}' | '\u{000C}' | '\u{2028}' | '\u{2029}'
+    )
+}
+
+/// The opening and closing quotes of a multi-line string.
+const MULTILINE_QUOTES: &str = "\"\"\"";
+
+/// The byte order mark, which is only allowed at the very start of a document.
+pub(crate) const BOM: char = '\u{FEFF}';
+
+/// The encoding whose byte order mark `bytes` start with, if it isn't UTF-8's.
+pub(crate) fn foreign_bom(bytes: &[u8]) -> Option<&'static str> {
+    // UTF-32LE's mark starts with UTF-16LE's, so it's checked first.
+    match bytes {
+        [0xFF, 0xFE, 0, 0, ..] => Some("UTF-32LE"),
+        [0, 0, 0xFE, 0xFF, ..] => Some("UTF-32BE"),
+        [0xFF, 0xFE, ..] => Some("UTF-16LE"),
+        [0xFE, 0xFF, ..] => Some("UTF-16BE"),
+        _ => None,
     }
 }
 
+/// Bidirectional control characters, which could make the source look different from how it is
+/// parsed.
+pub(crate) const fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{200E}'..='\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Code points that can't appear literally anywhere in a document. Surrogates are disallowed too,
+/// but they can't be part of a `str` in the first place.
 pub(crate) const fn is_disallowed(c: char) -> bool {
-    match c as u32 {
-        0..=8 => true,
-        0x7F => true,
-        0xD800..=0xDFFF => true,
-        // TODO: add more
-        _ => false,
-    }
+    matches!(c, '\u{0}'..='\u{8}' | '\u{E}'..='\u{1F}' | '\u{7F}' | BOM) || is_bidi_control(c)
+}
+
+/// Looks for a code point that can't appear literally in a document, locating it in `text`.
+/// If `text` is at the start of the document, it may start with a [`BOM`].
+pub(crate) fn find_disallowed(text: &str, at_document_start: bool) -> Option<Located> {
+    text.char_indices().find_map(|(i, c)| {
+        let cause = match c {
+            BOM if i == 0 && at_document_start => return None,
+            BOM => MisplacedBom,
+            c if is_bidi_control(c) => BidiControlCharacter { c },
+            c if is_disallowed(c) => DisallowedCharacter { c },
+            _ => return None,
+        };
+        Some(cause.at(i))
+    })
 }
 
+/// Keywords as written before KDL v2, which can't be identifiers so they aren't mistaken for the
+/// `#` prefixed ones.
+const LEGACY_KEYWORDS: [&str; 6] = ["true", "false", "null", "inf", "-inf", "nan"];
+
 pub(crate) const fn is_non_identifier(c: char) -> bool {
     match c {
         '(' | ')' | '{' | '}' | '[' | ']' | '/' | '\\' | '"' | '#' | ';' => true,
@@ -62,7 +109,7 @@ pub(crate) const fn is_non_identifier(c: char) -> bool {
 
 use ownable::IntoOwned;
 
-#[derive(IntoOwned, Clone, PartialEq, Eq, Debug)]
+#[derive(IntoOwned, Clone, Default, PartialEq, Eq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct KdlString<'text> {
     pub string: Text<'text>,
@@ -83,84 +130,513 @@ impl<'text> From<&'text str> for KdlString<'text> {
     }
 }
 
+/// Checks that `identifier`, made of characters identifiers may have, isn't one of the words that
+/// can't be identifiers.
+fn check_identifier(identifier: &str) -> Result<(), ParseErrorCause> {
+    // Keywords are prefixed with `#` in KDL v2, so bare ones are likely a mistake.
+    if let Some(keyword) = LEGACY_KEYWORDS
+        .into_iter()
+        .find(|keyword| *keyword == identifier)
+    {
+        return Err(ParseErrorCause::LegacyKeyword { keyword });
+    }
+
+    // Identifiers can't start like numbers (e.g. `1a`, `-1a` or `.1a`), so they're never
+    // mistaken for them.
+    let unsigned = identifier.strip_prefix(is_sign).unwrap_or(identifier);
+    let undotted = unsigned.strip_prefix('.').unwrap_or(unsigned);
+    if undotted.starts_with(is_digit) {
+        return Err(NumberLikeIdentifier {
+            identifier: identifier.into(),
+        });
+    }
+    Ok(())
+}
+
+/// Whether `text` can be written as an identifier string, without quotes.
+pub(crate) fn is_identifier(text: &str) -> bool {
+    !text.is_empty()
+        && !text.contains(|c| is_non_identifier(c) || is_disallowed(c))
+        && check_identifier(text).is_ok()
+}
+
+/// Writes `text` as a string: as an identifier if it can be one, or else as
+/// [`write_quoted_string`] does.
+pub(crate) fn write_string(f: &mut impl std::fmt::Write, text: &str) -> std::fmt::Result {
+    if is_identifier(text) {
+        return f.write_str(text);
+    }
+    write_quoted_string(f, text)
+}
+
+/// Writes `text` as a raw string if it has quotes or backslashes but nothing that must be escaped,
+/// or else as a quoted string.
+pub(crate) fn write_quoted_string(f: &mut impl std::fmt::Write, text: &str) -> std::fmt::Result {
+    let escaped = |c| is_newline(c) || is_disallowed(c);
+    if text.contains(['"', '\\']) && !text.contains(escaped) {
+        // Enough hashes that no quote in the string can close it.
+        let mut hashes = "#".to_string();
+        while text.contains(&format!("\"{hashes}")) {
+            hashes.push('#');
+        }
+        return write!(f, "{hashes}\"{text}\"{hashes}");
+    }
+    f.write_char('"')?;
+    for c in text.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            '\u{0008}' => f.write_str("\\b")?,
+            '\u{000C}' => f.write_str("\\f")?,
+            c if escaped(c) => write!(f, "\\u{{{:X}}}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
 pub(crate) trait ParseString<'text>: Buffer<'text> {
+    /// Looks for whitespace, returning its length in bytes.
     fn peek_whitespace(&self) -> Item<usize> {
-        let amount = self
-            .remaining_text()
-            .chars()
-            .take_while(|c| is_whitespace(*c))
-            .count();
-        item(amount, 0..amount)
+        let text = self.remaining_text();
+        let len = text.find(|c| !is_whitespace(c)).unwrap_or(text.len());
+        item(len, 0..len)
     }
 
-    fn peek_blankspace(&self) -> Item<usize> {
-        let mut char_count = 0;
-        let mut space_amount = 0;
-        // look for the previously visited char to check if it was a newline
-        //        if self
-        //            .text()
-        //            .chars()
-        //            .last()
-        //            .map(is_newline)
-        //            .unwrap_or_default()
-        //        {
-        //            visited_newline = true;
-        //        }
-        for (count, c) in self.remaining_text().chars().enumerate() {
-            if is_whitespace(c) {
-                char_count = count + 1;
-                if c == '\t' {
-                    space_amount += 4;
-                } else {
-                    space_amount += 1;
-                }
-            } else if is_newline(c) {
-                char_count = count + 1;
-                space_amount = 0;
+    /// Looks for a line continuation: a `\` followed by whitespace, comments and then a newline
+    /// (or the end of the text), so the node goes on in the next line.
+    fn peek_line_continuation(&self) -> ParseResult<Item<()>> {
+        let mut acc = self.sub_accumulator();
+        if acc.peek_char() != Some('\\') {
+            return Ok(None);
+        }
+        acc.consume_next_char();
+        loop {
+            if let Some((_, range)) = acc.peek_whitespace()
+                && !range.is_empty()
+            {
+                acc.consume_range(&range);
+            } else if let Some((_, range)) = acc.peek_comment()? {
+                acc.consume_range(&range);
             } else {
                 break;
             }
         }
-        item(space_amount, 0..char_count)
+        if let Some(newline) = acc.expect_sequence("\r\n") {
+            acc.consume_range(&newline);
+        } else if let Some(c) = acc.peek_char() {
+            if !is_newline(c) {
+                return Err(ParseErrorCause::expected(error::Expected::Newline, c).at(acc.end()));
+            }
+            acc.consume_next_char();
+        }
+        Ok(item((), acc.range()))
     }
 
+    /// Looks for whitespace and newlines, returning how much the next line is indented by.
+    /// If `skip_comments` is set, comments are also considered blank space.
+    fn peek_blankspace(&self, skip_comments: bool) -> ParseResult<Item<usize>> {
+        let mut acc = self.sub_accumulator();
+        let mut space_amount = 0;
+        // Whitespace after a comment doesn't count as indentation.
+        let mut after_comment = false;
+        loop {
+            if skip_comments && let Some((_, range)) = acc.peek_comment()? {
+                acc.consume_range(&range);
+                after_comment = true;
+                continue;
+            }
+            match acc.peek_char() {
+                Some(c) if is_newline(c) => {
+                    space_amount = 0;
+                    after_comment = false;
+                }
+                Some(c) if is_whitespace(c) && after_comment => (),
+                Some('\t') => space_amount += 4,
+                Some(c) if is_whitespace(c) => space_amount += 1,
+                _ => break,
+            }
+            acc.consume_next_char();
+        }
+        Ok(item(space_amount, acc.range()))
+    }
+
+    /// Consumes whitespace, along with any line continuations in it.
     fn consume_whitespace(&mut self) -> ParseResult<()> {
-        let v = self.peek_whitespace().ok_or_eof()?;
-        self.advance_bytes(v.0);
-        Ok(())
+        loop {
+            let (_, range) = self.peek_whitespace().ok_or_eof()?;
+            self.consume_range(&range);
+            let continuation = self
+                .peek_line_continuation()
+                .map_err(|error| error.offset_by(self.end()))?;
+            let Some((_, range)) = continuation else {
+                return Ok(());
+            };
+            self.consume_range(&range);
+        }
+    }
+
+    /// Checks whether the remaining text starts with a raw string, e.g. `#"` or `##"`.
+    fn starts_raw_string(&self) -> bool {
+        let rem = self.remaining_text();
+        rem.starts_with('#') && rem.trim_start_matches('#').starts_with('"')
     }
 
     fn peek_string(&self) -> ParseResult<Ranged<KdlString<'text>>> {
-        let mut end_sequence = None;
-        let mut acc = self.sub_accumulator();
+        match self.peek_char() {
+            Some('"') => self.peek_quoted_string(),
+            Some('#') if self.starts_raw_string() => self.peek_raw_string(),
+            Some(c) if !is_non_identifier(c) => self.peek_identifier_string(),
+            found => Err(ParseErrorCause::expected(error::Expected::String, found).into()),
+        }
+    }
 
-        match acc.peek_char().ok_or(ParseErrorCause::NeedsMoreData)? {
-            q @ '"' => end_sequence = Some(q),
-            c if is_non_identifier(c) || is_digit(c) => {
-                return Err(ParseErrorCause::InvalidStringCharacter { c })
+    fn peek_identifier_string(&self) -> ParseResult<Ranged<KdlString<'text>>> {
+        let mut acc = self.sub_accumulator();
+        while let Some(c) = acc.consume_next_char() {
+            if is_non_identifier(c) {
+                acc.unconsume_char(c);
+                break;
             }
-            _ => (),
-        };
+        }
+
+        let identifier = acc.text();
+        check_identifier(identifier)?;
+        Ok((KdlString::from_str(identifier), acc.range()))
+    }
+
+    fn peek_quoted_string(&self) -> ParseResult<Ranged<KdlString<'text>>> {
+        if self.remaining_text().starts_with(MULTILINE_QUOTES) {
+            return self.peek_multiline_string(None);
+        }
+        let mut acc = self.sub_accumulator();
+        acc.consume_next_char();
 
+        let start = acc.end();
+        acc.consume_escaped_until("\"", false)?;
+        let content = &acc.text()[start..];
         acc.consume_next_char();
 
-        while let Some(c) = acc.consume_next_char() {
-            // TODO: handle escape sequences
-            match end_sequence {
-                // Dquoted string
-                Some(end_sequence) if end_sequence == c => break,
-                // Indentifier string
-                None if is_non_identifier(c) => {
-                    acc.unconsume_char(c);
-                    break;
+        let string = unescape(content).map_err(|error| error.offset_by(start))?;
+        Ok((KdlString { string }, acc.range()))
+    }
+
+    /// Parses a raw string, which ends at a `"` followed by as many `#` as the string started with.
+    /// Since raw strings have no escapes, their contents are always borrowed.
+    fn peek_raw_string(&self) -> ParseResult<Ranged<KdlString<'text>>> {
+        let mut acc = self.sub_accumulator();
+        let hashes = acc
+            .remaining_text()
+            .chars()
+            .take_while(|c| *c == '#')
+            .count();
+        if acc.remaining_text()[hashes..].starts_with(MULTILINE_QUOTES) {
+            return self.peek_multiline_string(Some(hashes));
+        }
+        // Skips the hashes and the opening quote.
+        acc.advance_bytes(hashes + 1);
+
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        let content = acc.remaining_text();
+        let len = content
+            .find(&terminator)
+            .ok_or_else(|| unclosed(&terminator).at(acc.base().len()))?;
+        let content = &content[..len];
+        if let Some(newline) = content.find(is_newline) {
+            return Err(NewlineInString.at(acc.end() + newline));
+        }
+        acc.advance_bytes(len + terminator.len());
+
+        Ok((KdlString::from_str(content), acc.range()))
+    }
+
+    /// Parses a multi-line string, which is raw if `hashes` (the amount of `#` before it) is
+    /// present.
+    ///
+    /// The indentation of the line the closing quotes are in is removed from every other line, and
+    /// the newlines right after the opening quotes and right before the closing line are not part
+    /// of the string. Whitespace escapes are resolved before that, and all other escapes after.
+    fn peek_multiline_string(
+        &self,
+        hashes: Option<usize>,
+    ) -> ParseResult<Ranged<KdlString<'text>>> {
+        let mut acc = self.sub_accumulator();
+        let opening_len = hashes.unwrap_or(0) + MULTILINE_QUOTES.len();
+        acc.advance_bytes(opening_len);
+        let terminator = format!("{MULTILINE_QUOTES}{}", "#".repeat(hashes.unwrap_or(0)));
+
+        // The opening quotes must be immediately followed by a newline.
+        match acc.consume_next_char() {
+            Some('\r') if acc.peek_char() == Some('\n') => {
+                acc.consume_next_char();
+            }
+            Some(c) if is_newline(c) => (),
+            Some(_) => return Err(ContentOnOpeningLine.at(opening_len)),
+            None => return Err(unclosed(&terminator).at(acc.end())),
+        }
+
+        let body_start = acc.end();
+        if hashes.is_some() {
+            let len = acc
+                .remaining_text()
+                .find(&terminator)
+                .ok_or_else(|| unclosed(&terminator).at(acc.base().len()))?;
+            acc.advance_bytes(len);
+        } else {
+            acc.consume_escaped_until(&terminator, true)?;
+        }
+        let body = &acc.text()[body_start..];
+        acc.advance_bytes(terminator.len());
+
+        let string = if hashes.is_some() {
+            dedent_and_unescape(body, true)
+        } else {
+            match resolve_whitespace_escapes(body) {
+                (Cow::Borrowed(body), _) => dedent_and_unescape(body, false),
+                // Errors happen in the resolved text, so their location must be mapped back to
+                // where the escapes were.
+                (Cow::Owned(resolved), removed) => dedent_and_unescape(&resolved, false)
+                    .map(|string| Cow::Owned(string.into_owned()))
+                    .map_err(|error| {
+                        let before = removed.iter().take_while(|(at, _)| *at <= error.at);
+                        let amount = before.last().map_or(0, |(_, amount)| *amount);
+                        error.offset_by(amount)
+                    }),
+            }
+        };
+        let string = string.map_err(|error| error.offset_by(body_start))?;
+        Ok((KdlString { string }, acc.range()))
+    }
+
+    /// Consumes the body of a quoted string until `terminator` is found, skipping over escapes.
+    fn consume_escaped_until(&mut self, terminator: &str, allow_newlines: bool) -> ParseResult<()> {
+        while !self.remaining_text().starts_with(terminator) {
+            let c = self
+                .consume_next_char()
+                .ok_or_else(|| unclosed(terminator).at(self.end()))?;
+            if c == '\\' {
+                let c = self
+                    .consume_next_char()
+                    .ok_or_else(|| unclosed(terminator).at(self.end()))?;
+                // Whitespace escapes may span multiple lines, even in single-line strings.
+                if is_whitespace(c) || is_newline(c) {
+                    self.consume_escaped_whitespace();
                 }
-                // If the char isn't special, keep consuming it.
-                _ => (),
+            } else if is_newline(c) && !allow_newlines {
+                return Err(NewlineInString.at(self.end() - c.len_utf8()));
             }
         }
+        Ok(())
+    }
 
-        Ok((KdlString::from_str(acc.text()), acc.range()))
+    /// Consumes all whitespace, including newlines, up to the next non-whitespace character.
+    fn consume_escaped_whitespace(&mut self) {
+        while let Some(c) = self.consume_next_char() {
+            if !is_whitespace(c) && !is_newline(c) {
+                self.unconsume_char(c);
+                break;
+            }
+        }
+    }
+
+    /// Consumes an escape sequence whose `\\` was just consumed, pushing the character it
+    /// represents into `buf`.
+    fn consume_escape(&mut self, buf: &mut String) -> ParseResult<()> {
+        let start = self.end() - 1;
+        let c = self
+            .consume_next_char()
+            .ok_or_else(|| NeedsMoreData.at(self.end()))?;
+        let escaped = match c {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '\\' => '\\',
+            '"' => '"',
+            'b' => '\u{0008}',
+            'f' => '\u{000C}',
+            's' => ' ',
+            'u' => self
+                .consume_unicode_escape()
+                .map_err(|cause| cause.at(start))?,
+            // Whitespace escapes discard all whitespace up to the next non-whitespace character.
+            c if is_whitespace(c) || is_newline(c) => {
+                self.consume_escaped_whitespace();
+                return Ok(());
+            }
+            c => return Err(InvalidEscape { c }.at(start)),
+        };
+        buf.push(escaped);
+        Ok(())
+    }
+
+    /// Consumes the `{XXXXXX}` part of an unicode escape.
+    fn consume_unicode_escape(&mut self) -> Result<char, ParseErrorCause> {
+        if self.consume_next_char() != Some('{') {
+            return Err(InvalidUnicodeEscape);
+        }
+        let digits = self
+            .remaining_text()
+            .chars()
+            .take_while(char::is_ascii_hexdigit)
+            .count();
+        if !(1..=6).contains(&digits) {
+            return Err(InvalidUnicodeEscape);
+        }
+        let value = u32::from_str_radix(&self.remaining_text()[..digits], 16)
+            .expect("Hex digits should always be parseable");
+        self.advance_bytes(digits);
+        if self.consume_next_char() != Some('}') {
+            return Err(InvalidUnicodeEscape);
+        }
+        // Surrogates and values past `10FFFF` are not valid characters.
+        char::from_u32(value).ok_or(InvalidUnicodeEscape)
     }
 }
 
 impl<'text, B> ParseString<'text> for B where B: Buffer<'text> {}
+
+/// The error for a string that's missing its closing `quote`.
+fn unclosed(quote: &str) -> ParseErrorCause {
+    ParseErrorCause::expected(
+        error::Expected::ClosingQuote {
+            quote: quote.to_string(),
+        },
+        None,
+    )
+}
+
+/// Processes all escapes in `text`, which is only copied if any escape is found.
+fn unescape(text: &str) -> ParseResult<Cow<'_, str>> {
+    if !text.contains('\\') {
+        return Ok(Cow::Borrowed(text));
+    }
+    let mut acc = Acc::new(text);
+    let mut unescaped = String::with_capacity(text.len());
+    while let Some(c) = acc.consume_next_char() {
+        if c == '\\' {
+            acc.consume_escape(&mut unescaped)?;
+        } else {
+            unescaped.push(c);
+        }
+    }
+    Ok(Cow::Owned(unescaped))
+}
+
+/// Removes the whitespace escapes from the body of a multi-line string, which is only copied if
+/// any is found, so they can join lines before it's dedented.
+///
+/// Along with it comes where each removed escape was in the new text, and how many bytes were
+/// removed up to there.
+fn resolve_whitespace_escapes(body: &str) -> (Cow<'_, str>, Vec<(usize, usize)>) {
+    let mut resolved = String::new();
+    let mut removed = vec![];
+    let mut copied = 0;
+    let mut acc = Acc::new(body);
+    while let Some(c) = acc.consume_next_char() {
+        if c != '\\' {
+            continue;
+        }
+        let start = acc.end() - 1;
+        // Other escapes are processed later, but what they escape is still skipped, so that an
+        // escaped `\\` doesn't start another one.
+        if acc
+            .consume_next_char()
+            .is_some_and(|c| is_whitespace(c) || is_newline(c))
+        {
+            acc.consume_escaped_whitespace();
+            resolved.push_str(&body[copied..start]);
+            copied = acc.end();
+            removed.push((resolved.len(), copied - resolved.len()));
+        }
+    }
+    if removed.is_empty() {
+        return (Cow::Borrowed(body), removed);
+    }
+    resolved.push_str(&body[copied..]);
+    (Cow::Owned(resolved), removed)
+}
+
+/// Dedents the body of a multi-line string and joins its lines back, processing its escapes unless
+/// it's `raw`.
+fn dedent_and_unescape(body: &str, raw: bool) -> ParseResult<Cow<'_, str>> {
+    let lines = dedent(body)?;
+    let joined = match lines.as_slice() {
+        [] => Cow::Borrowed(""),
+        [(_, line)] => Cow::Borrowed(*line),
+        lines => Cow::Owned(
+            lines
+                .iter()
+                .map(|(_, line)| *line)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+    };
+    if raw {
+        return Ok(joined);
+    }
+
+    // Escape errors happen in the dedented text, so their location must be mapped back to the
+    // line they're in.
+    let locate = |error: Located| {
+        let mut line_start = 0;
+        for (start, line) in &lines {
+            if error.at <= line_start + line.len() {
+                return error.offset_by(start - line_start);
+            }
+            line_start += line.len() + 1;
+        }
+        error
+    };
+    match joined {
+        Cow::Borrowed(text) => unescape(text).map_err(locate),
+        Cow::Owned(text) => Ok(Cow::Owned(unescape(&text).map_err(locate)?.into_owned())),
+    }
+}
+
+/// Splits `text` into lines, along with where each of them starts. `\r\n` counts as one newline.
+fn split_lines(text: &str) -> Vec<(usize, &str)> {
+    let mut lines = vec![];
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if !is_newline(c) {
+            continue;
+        }
+        lines.push((start, &text[start..i]));
+        start = i + c.len_utf8();
+        if c == '\r' && chars.next_if(|(_, c)| *c == '\n').is_some() {
+            start += 1;
+        }
+    }
+    lines.push((start, &text[start..]));
+    lines
+}
+
+/// Removes the indentation of the last line of a multi-line string's `body` from all other lines,
+/// returning them along with where their dedented text starts in `body`.
+///
+/// Lines with only whitespace become empty, and all other lines must start with the exact same
+/// whitespace as the last one.
+fn dedent(body: &str) -> ParseResult<Vec<(usize, &str)>> {
+    let mut lines = split_lines(body);
+    let (closing_start, indentation) = lines.pop().expect("There's always at least one line");
+    if let Some(i) = indentation.find(|c| !is_whitespace(c)) {
+        return Err(ContentOnClosingLine.at(closing_start + i));
+    }
+    lines
+        .into_iter()
+        .map(|(start, line)| {
+            if line.chars().all(is_whitespace) {
+                Ok((start + line.len(), ""))
+            } else {
+                match line.strip_prefix(indentation) {
+                    Some(line) => Ok((start + indentation.len(), line)),
+                    None => Err(InconsistentIndentation.at(start)),
+                }
+            }
+        })
+        .collect()
+}
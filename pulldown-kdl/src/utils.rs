@@ -2,43 +2,15 @@ use std::ops::{Index, Range};
 
 use crate::ParseErrorCause;
 
-pub(crate) fn first_char(seq: &[u8]) -> Option<char> {
-    let len = utf8_byte_len(*seq.get(0)?) as usize;
-    std::str::from_utf8(&seq[..len]).ok()?.chars().next()
-}
-
-pub(crate) fn utf8_byte_len(i: u8) -> u8 {
-    if (i >> 7 & 1) == 0 {
-        return 1;
-    } else if i >> 5 == 0b110 {
-        2
-    } else if i >> 4 == 0b1110 {
-        3
-    } else if i >> 3 == 0b11110 {
-        4
-    } else {
-        unreachable!("Invalid utf-8 sequence: {}", i)
-    }
-}
-
 pub(crate) trait Buffer<'a> {
     fn base(&self) -> &'a str;
     fn end(&self) -> usize;
     fn set_end(&mut self, new_end: usize);
 
-    fn buffer(&self) -> &dyn Buffer<'a>;
-
     fn advance_bytes(&mut self, amount: usize) {
         self.set_end(self.end() + amount);
     }
 
-    fn next_char_len(&self) -> usize {
-        match self.remaining_bytes().get(0) {
-            Some(c) => utf8_byte_len(*c) as usize,
-            _ => 0,
-        }
-    }
-
     fn consume_next_char(&mut self) -> Option<char> {
         let c = self.peek_char()?;
         self.advance_bytes(c.len_utf8());
@@ -64,12 +36,8 @@ pub(crate) trait Buffer<'a> {
         Some(0..seq.len())
     }
 
-    fn peek_byte(&self) -> Option<u8> {
-        self.bytes().get(self.end()).copied()
-    }
-
     fn peek_char(&self) -> Option<char> {
-        first_char(self.remaining_bytes())
+        self.remaining_text().chars().next()
     }
 
     fn unconsume_char(&mut self, c: char) {
@@ -77,10 +45,6 @@ pub(crate) trait Buffer<'a> {
         debug_assert_eq!(self.peek_char().unwrap(), c);
     }
 
-    fn remaining_bytes(&self) -> &'a [u8] {
-        &self.base().as_bytes()[self.end()..]
-    }
-
     fn remaining_text(&self) -> &'a str {
         &self.base()[self.end()..]
     }
@@ -107,7 +71,7 @@ pub(crate) trait OptionExt<'text, T>: Sized {
 
 impl<'text, T> OptionExt<'text, T> for Option<T> {
     fn ok_or_cause(self, cause: ParseErrorCause) -> Result<T, ParseErrorCause> {
-        self.ok_or_else(|| cause)
+        self.ok_or(cause)
     }
 }
 
@@ -128,10 +92,6 @@ impl<'a> Buffer<'a> for Acc<'a> {
         self.base
     }
 
-    fn buffer(&self) -> &dyn Buffer<'a> {
-        self
-    }
-
     fn end(&self) -> usize {
         self.end
     }
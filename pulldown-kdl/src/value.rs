@@ -1,13 +1,13 @@
 use std::fmt::Display;
 
-use crate::KdlString;
+use crate::{KdlNumber, KdlString};
 use ownable::IntoOwned;
 
 #[derive(IntoOwned, Debug, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum KdlValue<'text> {
     String(KdlString<'text>),
-    Num(f64),
+    Num(KdlNumber<'text>),
     Bool(bool),
     Null,
 }
@@ -19,8 +19,8 @@ impl<'text> Display for KdlValue<'text> {
         match self {
             String(string) => string.string.fmt(f),
             Num(v) => v.fmt(f),
-            Bool(v) => v.fmt(f),
-            Null => write!(f, "null"),
+            Bool(v) => write!(f, "#{v}"),
+            Null => write!(f, "#null"),
         }
     }
 }
@@ -0,0 +1,840 @@
+use std::fmt::Write as _;
+use std::{fmt, io};
+
+use crate::string::{is_newline, write_quoted_string, write_string};
+use crate::{CommentKind, Event, KdlNodeEntry, KdlString, KdlValue, Ranged};
+
+/// An error found while writing a document: either an event can't come where it was written, or
+/// the output failed.
+#[derive(Debug)]
+pub enum WriteError {
+    /// The event can't come next in a document, such as an entry after the node's children, or
+    /// the document was finished before it ended, in which case there's no event.
+    Misplaced {
+        event: Option<Box<Event<'static>>>,
+        reason: &'static str,
+    },
+    Io(io::Error),
+    Fmt(fmt::Error),
+}
+
+impl From<io::Error> for WriteError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<fmt::Error> for WriteError {
+    fn from(error: fmt::Error) -> Self {
+        Self::Fmt(error)
+    }
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Misplaced {
+                event: Some(event),
+                reason,
+            } => write!(f, "Can't write {event:?}: {reason}"),
+            Self::Misplaced {
+                event: None,
+                reason,
+            } => write!(f, "Can't finish the document: {reason}"),
+            Self::Io(error) => write!(f, "Failed to write the document: {error}"),
+            Self::Fmt(error) => write!(f, "Failed to write the document: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for WriteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Misplaced { .. } => None,
+            Self::Io(error) => Some(error),
+            Self::Fmt(error) => Some(error),
+        }
+    }
+}
+
+/// Where a [`Writer`] writes its text: any [`fmt::Write`], such as a [`String`], or any
+/// [`io::Write`] wrapped in an [`IoWriter`].
+pub trait Output {
+    fn write_text(&mut self, text: &str) -> Result<(), WriteError>;
+}
+
+impl<W: fmt::Write> Output for W {
+    fn write_text(&mut self, text: &str) -> Result<(), WriteError> {
+        Ok(self.write_str(text)?)
+    }
+}
+
+/// Lets a [`Writer`] write to an [`io::Write`], such as a file or [`io::stdout`].
+pub struct IoWriter<W>(pub W);
+
+impl<W: io::Write> Output for IoWriter<W> {
+    fn write_text(&mut self, text: &str) -> Result<(), WriteError> {
+        Ok(self.0.write_all(text.as_bytes())?)
+    }
+}
+
+/// How a [`Writer`] indents children blocks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Indent {
+    /// This many spaces per children block.
+    Spaces(usize),
+    /// A tab per children block.
+    Tabs,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Self::Spaces(4)
+    }
+}
+
+/// How a [`Writer`] writes strings.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Quotes {
+    /// Strings are only quoted if they can't be identifiers.
+    #[default]
+    Minimal,
+    /// String values are always quoted, while node names, property keys and type annotations are
+    /// only quoted if they need to be.
+    Values,
+}
+
+/// Changes how a [`Writer`] lays out a document.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct WriterOptions {
+    pub indent: Indent,
+    pub quotes: Quotes,
+}
+
+/// What separates the last thing written from the next one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Gap {
+    /// Nothing was written yet.
+    Start,
+    /// Nothing, as right after the indentation of a continued line.
+    None,
+    Space,
+    /// A new line was just started, so only the indentation is missing.
+    Line,
+    /// A new line must be started, as the source had one there.
+    Break,
+    /// A children block was just opened, so a new line must be started unless it's closed right
+    /// away.
+    Block,
+}
+
+/// The children blocks a node has, which no entry can come after.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+enum Children {
+    #[default]
+    None,
+    /// Only children blocks commented out with a slashdash, which may come before the real one.
+    Commented,
+    /// The real children block, which only commented out ones may come after.
+    Written,
+}
+
+/// What a slashdash comments out, once its event comes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Target {
+    Node,
+    Entry,
+    Children,
+}
+
+/// A slashdash whose [`Event::SlashdashEnd`] didn't come yet.
+#[derive(Clone, Copy, Debug)]
+struct Slashdash {
+    /// How many documents were open when it started.
+    documents: usize,
+    target: Option<Target>,
+    /// Whether the item it comments out ended, so only its [`Event::SlashdashEnd`] can come next.
+    ended: bool,
+}
+
+/// Writes a document from its events, the inverse of a [`Parser`](crate::Parser).
+///
+/// Events are checked to come in an order that makes a valid document, so a node can't start
+/// inside another's entries, entries can't come after children blocks, and so on. The layout is
+/// the writer's own: every node goes in its own line, indented as [`WriterOptions::indent`] says,
+/// entries are separated by single spaces, properties are written as `key=value` and strings are
+/// quoted as [`WriterOptions::quotes`] says. Since it's chosen by the writer,
+/// [`Event::Indentation`] is ignored, as is [`Event::Error`], whose text is lost.
+///
+/// Line comments inside a node that goes on after them are written after a line continuation
+/// (`\`), so they don't end the node.
+pub struct Writer<W> {
+    output: W,
+    /// What each children block is indented with.
+    indent: String,
+    quotes: Quotes,
+    /// The text of the current event, written to the output all at once.
+    text: String,
+    gap: Gap,
+    /// How many blank lines to write before the next line, if it starts with a node or comment.
+    blank_lines: usize,
+    /// How many documents are open, counting the root one.
+    documents: usize,
+    /// Whether the root document ended.
+    ended: bool,
+    /// The children blocks of the node open in the innermost document, if any.
+    node: Option<Children>,
+    /// The children blocks of the nodes whose children blocks are open.
+    parents: Vec<Children>,
+    slashdashes: Vec<Slashdash>,
+    /// Whether the `/-` of the last slashdash is still to be written, right before the item it
+    /// comments out so that no comment comes in between.
+    slashdash: bool,
+    /// A line comment inside the open node, written once it's known whether the node ends after
+    /// it.
+    line_comment: Option<String>,
+}
+
+impl<W: Output> Writer<W> {
+    pub fn new(output: W) -> Self {
+        Self::with_options(output, WriterOptions::default())
+    }
+
+    pub fn with_options(output: W, options: WriterOptions) -> Self {
+        let indent = match options.indent {
+            Indent::Spaces(width) => " ".repeat(width),
+            Indent::Tabs => "\t".to_owned(),
+        };
+        Self {
+            output,
+            indent,
+            quotes: options.quotes,
+            text: String::new(),
+            gap: Gap::Start,
+            blank_lines: 0,
+            documents: 0,
+            ended: false,
+            node: None,
+            parents: Vec::new(),
+            slashdashes: Vec::new(),
+            slashdash: false,
+            line_comment: None,
+        }
+    }
+
+    /// Gives back the output the document was written to, even if it didn't end.
+    pub fn into_inner(self) -> W {
+        self.output
+    }
+
+    /// Gives back the output the document was written to, checking that the document ended so
+    /// that every node, children block and slashdash is closed.
+    pub fn finish(self) -> Result<W, WriteError> {
+        let reason = if !self.ended {
+            "The document didn't end"
+        } else if !self.slashdashes.is_empty() {
+            "The last slashdash didn't end"
+        } else {
+            return Ok(self.output);
+        };
+        Err(WriteError::Misplaced {
+            event: None,
+            reason,
+        })
+    }
+
+    /// Writes every event, stopping at the first error.
+    pub fn write_events<'text>(
+        &mut self,
+        events: impl IntoIterator<Item = Event<'text>>,
+    ) -> Result<(), WriteError> {
+        events
+            .into_iter()
+            .try_for_each(|event| self.write_event(event))
+    }
+
+    /// Writes the next event of the document.
+    pub fn write_event(&mut self, event: Event<'_>) -> Result<(), WriteError> {
+        self.text.clear();
+        self.push(&event).map_err(|reason| WriteError::Misplaced {
+            event: Some(Box::new(event.into_owned())),
+            reason,
+        })?;
+        self.output.write_text(&self.text)
+    }
+
+    /// Adds the text of an event, or returns why it can't come next.
+    fn push(&mut self, event: &Event<'_>) -> Result<(), &'static str> {
+        match event {
+            Event::Indentation(_) => return Ok(()),
+            Event::Error => {
+                // The item a slashdash was waiting for may have been skipped, which then ends it.
+                if let Some(slashdash) = self.slashdashes.last_mut()
+                    && slashdash.target.is_none()
+                {
+                    slashdash.ended = true;
+                    self.slashdash = false;
+                }
+                return Ok(());
+            }
+            _ => (),
+        }
+        if self.ended {
+            return Err("The document already ended");
+        }
+        if self.documents == 0 && *event != Event::StartDocument {
+            return Err("The document didn't start yet");
+        }
+        let structural = !matches!(event, Event::Comment { .. } | Event::SlashdashEnd);
+        if structural && self.slashdashes.last().is_some_and(|s| s.ended) {
+            return Err("The item commented out by the last slashdash ended, so it must end too");
+        }
+        // Whether the last slashdash is waiting for the item it comments out.
+        let aiming = self
+            .slashdashes
+            .last()
+            .is_some_and(|s| s.target.is_none() && !s.ended);
+        match event {
+            Event::StartDocument if self.documents == 0 => self.documents = 1,
+            Event::StartDocument => {
+                let Some(children) = self.node else {
+                    return Err("A children block must belong to a node");
+                };
+                if children == Children::Written && !aiming {
+                    return Err("A node can't have more than one children block");
+                }
+                self.node = None;
+                if aiming {
+                    self.aim(Target::Children);
+                    self.parents.push(children.max(Children::Commented));
+                } else {
+                    self.parents.push(Children::Written);
+                }
+                self.start_item();
+                self.text.push('{');
+                self.gap = Gap::Block;
+                self.documents += 1;
+            }
+            Event::EndDocument => {
+                if self.node.is_some() {
+                    return Err("The open node must end before its document");
+                }
+                if let Some(slashdash) = self.slashdashes.last()
+                    && slashdash.documents == self.documents
+                {
+                    return Err("A slashdash must comment out a node, entry or children block");
+                }
+                self.documents -= 1;
+                if self.documents == 0 {
+                    self.ended = true;
+                    if !matches!(self.gap, Gap::Start | Gap::Line | Gap::Block) {
+                        self.text.push('\n');
+                    }
+                    return Ok(());
+                }
+                self.node = self.parents.pop();
+                self.blank_lines = 0;
+                match self.gap {
+                    Gap::Start | Gap::Block => (),
+                    Gap::Line => self.indent(0),
+                    Gap::None | Gap::Space | Gap::Break => {
+                        self.text.push('\n');
+                        self.indent(0);
+                    }
+                }
+                self.text.push('}');
+                self.gap = Gap::Space;
+                self.end_slashdash(Target::Children);
+            }
+            Event::NodeName { ty, name } => {
+                if self.node.is_some() {
+                    return Err("A node can't start inside another node");
+                }
+                if aiming {
+                    self.aim(Target::Node);
+                }
+                self.node = Some(Children::None);
+                self.start_item();
+                self.write_ty(ty);
+                self.write_string(name);
+                self.gap = Gap::Space;
+            }
+            Event::NodeEntry(entry) => {
+                match self.node {
+                    None => return Err("An entry must belong to a node"),
+                    Some(Children::None) => (),
+                    Some(_) => return Err("Entries must come before the node's children"),
+                }
+                if aiming {
+                    self.aim(Target::Entry);
+                    self.end_slashdash(Target::Entry);
+                }
+                self.start_item();
+                match entry {
+                    KdlNodeEntry::Argument { ty, value } => {
+                        self.write_ty(ty);
+                        self.write_value(value);
+                    }
+                    KdlNodeEntry::Property { key, ty, value } => {
+                        self.write_string(key);
+                        self.text.push('=');
+                        self.write_ty(ty);
+                        self.write_value(value);
+                    }
+                }
+                self.gap = Gap::Space;
+            }
+            Event::NodeEnd { .. } => {
+                if self.node.is_none() {
+                    return Err("There's no node to end");
+                }
+                if aiming {
+                    return Err("A slashdash must comment out a node, entry or children block");
+                }
+                self.node = None;
+                self.flush_line_comment(true);
+                self.text.push('\n');
+                self.gap = Gap::Line;
+                self.end_slashdash(Target::Node);
+            }
+            Event::Comment {
+                text,
+                kind: CommentKind::Line,
+            } => {
+                if text.contains(is_newline) {
+                    return Err("A line comment can't span more than one line");
+                }
+                if self.node.is_some() {
+                    self.flush_line_comment(false);
+                    self.line_comment = Some(text.to_string());
+                } else {
+                    self.separate();
+                    writeln!(self.text, "//{text}").expect("Writing to a string doesn't fail");
+                    self.gap = Gap::Line;
+                }
+            }
+            Event::Comment {
+                text,
+                kind: CommentKind::Block,
+            } => {
+                if !is_block_comment_text(text) {
+                    return Err("The nested block comments of a block comment must be balanced");
+                }
+                self.flush_line_comment(false);
+                self.separate();
+                write!(self.text, "/*{text}*/").expect("Writing to a string doesn't fail");
+                self.gap = Gap::Space;
+            }
+            Event::SlashdashStart => {
+                if aiming {
+                    return Err("A slashdash can't comment out another slashdash");
+                }
+                self.slashdashes.push(Slashdash {
+                    documents: self.documents,
+                    target: None,
+                    ended: false,
+                });
+                self.slashdash = true;
+            }
+            Event::SlashdashEnd => match self.slashdashes.last() {
+                Some(slashdash) if slashdash.ended => {
+                    self.slashdashes.pop();
+                }
+                Some(_) => return Err("The item commented out by the last slashdash didn't end"),
+                None => return Err("There's no slashdash to end"),
+            },
+            Event::Indentation(_) | Event::Error => (),
+        }
+        Ok(())
+    }
+
+    /// Sets what the last slashdash comments out.
+    fn aim(&mut self, target: Target) {
+        if let Some(slashdash) = self.slashdashes.last_mut() {
+            slashdash.target = Some(target);
+        }
+    }
+
+    /// Marks the last slashdash as ended if it comments out a `target` that just ended in the
+    /// innermost document.
+    fn end_slashdash(&mut self, target: Target) {
+        if let Some(slashdash) = self.slashdashes.last_mut()
+            && slashdash.target == Some(target)
+            && slashdash.documents == self.documents
+        {
+            slashdash.ended = true;
+        }
+    }
+
+    /// Indents a new line, `extra` levels deeper than the innermost document.
+    fn indent(&mut self, extra: usize) {
+        for _ in 1..self.documents + extra {
+            self.text.push_str(&self.indent);
+        }
+    }
+
+    /// Starts writing a node, entry or children block, after any pending line comment and
+    /// slashdash.
+    fn start_item(&mut self) {
+        self.flush_line_comment(false);
+        self.separate();
+        if std::mem::take(&mut self.slashdash) {
+            self.text.push_str("/-");
+        }
+    }
+
+    /// Separates the next token from the last one.
+    fn separate(&mut self) {
+        match self.gap {
+            Gap::Start | Gap::None => (),
+            Gap::Space => self.text.push(' '),
+            Gap::Line | Gap::Break => {
+                if self.gap == Gap::Break {
+                    self.text.push('\n');
+                }
+                for _ in 0..self.blank_lines {
+                    self.text.push('\n');
+                }
+                self.indent(0);
+            }
+            Gap::Block => {
+                self.text.push('\n');
+                self.indent(0);
+            }
+        }
+        self.gap = Gap::None;
+        self.blank_lines = 0;
+    }
+
+    /// Keeps a line break that the source had between the items of a document, so an item after a
+    /// block comment goes in the next line, along with up to `max_blank_lines` of the blank lines
+    /// after it. Line breaks inside nodes and at the start of a document are left out.
+    pub(crate) fn break_line(&mut self, newlines: usize, max_blank_lines: usize) {
+        if newlines == 0 || self.node.is_some() || self.slashdash {
+            return;
+        }
+        match self.gap {
+            Gap::Space => self.gap = Gap::Break,
+            Gap::Line | Gap::Break => (),
+            Gap::Start | Gap::None | Gap::Block => return,
+        }
+        self.blank_lines = (newlines - 1).min(max_blank_lines);
+    }
+
+    /// Writes the pending line comment of the open node, either at the end of the node's line if
+    /// it `ends_node`, or else after a line continuation so the node goes on in the next line.
+    fn flush_line_comment(&mut self, ends_node: bool) {
+        let Some(comment) = self.line_comment.take() else {
+            return;
+        };
+        self.separate();
+        if ends_node {
+            write!(self.text, "//{comment}")
+        } else {
+            writeln!(self.text, "\\ //{comment}")
+        }
+        .expect("Writing to a string doesn't fail");
+        if !ends_node {
+            self.indent(1);
+        }
+    }
+
+    fn write_ty(&mut self, ty: &Option<Ranged<KdlString<'_>>>) {
+        if let Some((ty, _)) = ty {
+            self.text.push('(');
+            self.write_string(ty);
+            self.text.push(')');
+        }
+    }
+
+    fn write_string(&mut self, string: &KdlString<'_>) {
+        write_string(&mut self.text, &string.string).expect("Writing to a string doesn't fail");
+    }
+
+    fn write_value(&mut self, value: &KdlValue<'_>) {
+        match value {
+            KdlValue::String(string) if self.quotes == Quotes::Values => {
+                write_quoted_string(&mut self.text, &string.string)
+                    .expect("Writing to a string doesn't fail")
+            }
+            KdlValue::String(string) => self.write_string(string),
+            // Numbers and keywords are already written as they are in KDL.
+            value => write!(self.text, "{value}").expect("Writing to a string doesn't fail"),
+        }
+    }
+}
+
+/// Whether `/*{text}*/` is a single block comment, ending at its last `*/` because every `/*`
+/// in `text` is closed by a later `*/`.
+fn is_block_comment_text(text: &str) -> bool {
+    let closed = format!("{text}*/");
+    let mut rest = closed.as_str();
+    let mut depth = 1;
+    while let Some(c) = rest.chars().next() {
+        if depth == 0 {
+            return false;
+        }
+        if rest.starts_with("/*") {
+            depth += 1;
+            rest = &rest[2..];
+        } else if rest.starts_with("*/") {
+            depth -= 1;
+            rest = &rest[2..];
+        } else {
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    depth == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, ParserOptions};
+
+    fn write(text: &str, options: WriterOptions) -> String {
+        let parser_options = ParserOptions {
+            comments: true,
+            slashdash: true,
+            recover: false,
+        };
+        let mut writer = Writer::with_options(String::new(), options);
+        writer
+            .write_events(Parser::with_options(text, parser_options).map(|event| event.unwrap().0))
+            .unwrap();
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn writes_its_own_layout() {
+        let text = "a 1;b k=\"x y\" \"s\" {c;/-d 2}\n\n\n/* block */ e 3 // end\n";
+        assert_eq!(
+            write(text, WriterOptions::default()),
+            "a 1\nb k=\"x y\" s {\n    c\n    /-d 2\n}\n/* block */ e 3 // end\n"
+        );
+        let options = WriterOptions {
+            indent: Indent::Tabs,
+            quotes: Quotes::Values,
+        };
+        assert_eq!(
+            write("(t)a \"b\" k=c {\n  d e\n}", options),
+            "(t)a \"b\" k=\"c\" {\n\td \"e\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn continues_nodes_after_line_comments() {
+        let mut writer = Writer::new(String::new());
+        let comment = Event::Comment {
+            text: " line".into(),
+            kind: CommentKind::Line,
+        };
+        let events = [
+            Event::StartDocument,
+            node("a"),
+            comment.clone(),
+            argument(),
+            comment,
+            END,
+            Event::EndDocument,
+        ];
+        writer.write_events(events).unwrap();
+        assert_eq!(
+            writer.finish().unwrap(),
+            "a \\ // line\n    #null // line\n"
+        );
+    }
+
+    fn node(name: &str) -> Event<'_> {
+        Event::NodeName {
+            ty: None,
+            name: KdlString::from_str(name),
+        }
+    }
+
+    fn argument() -> Event<'static> {
+        Event::NodeEntry(KdlNodeEntry::Argument {
+            ty: None,
+            value: KdlValue::Null,
+        })
+    }
+
+    const END: Event = Event::NodeEnd { inline: false };
+
+    /// Writes `events`, returning why the last one can't come where it is.
+    fn misplaced(events: Vec<Event<'_>>) -> &'static str {
+        let mut writer = Writer::new(String::new());
+        let last = events.len() - 1;
+        for (i, event) in events.into_iter().enumerate() {
+            match writer.write_event(event) {
+                Ok(()) if i < last => (),
+                Err(WriteError::Misplaced { reason, .. }) if i == last => return reason,
+                result => panic!("Unexpected result at event {i}: {result:?}"),
+            }
+        }
+        unreachable!()
+    }
+
+    #[test]
+    fn rejects_misplaced_events() {
+        use Event::{EndDocument, SlashdashEnd, SlashdashStart, StartDocument};
+        let cases = [
+            (vec![node("a")], "didn't start yet"),
+            (
+                vec![StartDocument, EndDocument, StartDocument],
+                "already ended",
+            ),
+            (vec![StartDocument, argument()], "must belong to a node"),
+            (vec![StartDocument, StartDocument], "must belong to a node"),
+            (
+                vec![StartDocument, node("a"), node("b")],
+                "inside another node",
+            ),
+            (
+                vec![
+                    StartDocument,
+                    node("a"),
+                    StartDocument,
+                    EndDocument,
+                    argument(),
+                ],
+                "before the node's children",
+            ),
+            (
+                vec![
+                    StartDocument,
+                    node("a"),
+                    StartDocument,
+                    EndDocument,
+                    StartDocument,
+                ],
+                "more than one children block",
+            ),
+            (
+                vec![StartDocument, node("a"), EndDocument],
+                "must end before",
+            ),
+            (vec![StartDocument, END], "no node to end"),
+            (
+                vec![StartDocument, SlashdashStart, EndDocument],
+                "must comment out",
+            ),
+            (
+                vec![StartDocument, node("a"), SlashdashStart, END],
+                "must comment out",
+            ),
+            (
+                vec![StartDocument, SlashdashStart, SlashdashStart],
+                "another slashdash",
+            ),
+            (vec![StartDocument, SlashdashEnd], "no slashdash to end"),
+            (
+                vec![StartDocument, SlashdashStart, node("a"), SlashdashEnd],
+                "didn't end",
+            ),
+            (
+                vec![StartDocument, SlashdashStart, node("a"), END, node("b")],
+                "so it must end too",
+            ),
+            (
+                vec![
+                    StartDocument,
+                    Event::Comment {
+                        text: "a\nb".into(),
+                        kind: CommentKind::Line,
+                    },
+                ],
+                "more than one line",
+            ),
+        ];
+        let block_comments = [" a */ b ", " /* a ", " a /", "*/"];
+        let cases = cases.into_iter().chain(block_comments.map(|text| {
+            let comment = Event::Comment {
+                text: text.into(),
+                kind: CommentKind::Block,
+            };
+            (vec![StartDocument, comment], "must be balanced")
+        }));
+        for (events, reason) in cases {
+            let found = misplaced(events);
+            assert!(found.contains(reason), "{found:?} doesn't say {reason:?}");
+        }
+    }
+
+    /// Writes every fixture in a directory of the repository, which must all be written in full.
+    fn write_fixtures(dir: &str) {
+        // Errors are left out, but what's around them must still be written.
+        let options = ParserOptions {
+            comments: true,
+            slashdash: true,
+            recover: true,
+        };
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("..")
+            .join(dir);
+        let mut fixtures = 0;
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|extension| extension != "kdl") {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path).unwrap();
+            let mut writer = Writer::new(String::new());
+            for event in Parser::with_options(&text, options) {
+                let (event, _) =
+                    event.unwrap_or_else(|error| panic!("{}: {error}", path.display()));
+                writer
+                    .write_event(event)
+                    .unwrap_or_else(|error| panic!("{}: {error}", path.display()));
+            }
+            if let Err(error) = writer.finish() {
+                panic!("{}: {error}", path.display());
+            }
+            fixtures += 1;
+        }
+        assert!(fixtures > 0);
+    }
+
+    #[test]
+    fn writes_what_the_parser_reads() {
+        write_fixtures("tests");
+        write_fixtures("failing_tests");
+    }
+
+    #[test]
+    fn writes_nested_block_comments() {
+        let mut writer = Writer::new(String::new());
+        let comment = Event::Comment {
+            text: " a /* b */ *".into(),
+            kind: CommentKind::Block,
+        };
+        writer
+            .write_events([Event::StartDocument, comment, Event::EndDocument])
+            .unwrap();
+        assert_eq!(writer.finish().unwrap(), "/* a /* b */ **/\n");
+    }
+
+    #[test]
+    fn only_finishes_ended_documents() {
+        use Event::{EndDocument, SlashdashStart, StartDocument};
+        let cases = [
+            vec![],
+            vec![StartDocument],
+            vec![StartDocument, node("a"), StartDocument, node("b")],
+            vec![StartDocument, SlashdashStart],
+        ];
+        for events in cases {
+            let mut writer = Writer::new(String::new());
+            writer.write_events(events.clone()).unwrap();
+            match writer.finish() {
+                Err(WriteError::Misplaced { event: None, .. }) => (),
+                result => panic!("Finished {events:?} with {result:?}"),
+            }
+        }
+        let mut writer = Writer::new(String::new());
+        writer
+            .write_events([StartDocument, node("a"), END, EndDocument])
+            .unwrap();
+        assert_eq!(writer.finish().unwrap(), "a\n");
+    }
+}
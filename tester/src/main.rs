@@ -1,12 +1,32 @@
-use std::{any::Any, ffi::OsString, io::BufReader, path::Path};
+use std::{
+    any::Any,
+    ffi::OsString,
+    io::BufReader,
+    ops::Range,
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+};
+
+use futures_core::Stream;
+use futures_io::{AsyncBufRead, AsyncRead};
 
 use miette::IntoDiagnostic;
-use pulldown_kdl::{Event, KdlNodeEntry, KdlValue, ParseError, Parser, Ranged};
+use pulldown_kdl::{
+    AsyncReaderParser, CommentKind, Event, FormatError, FormatOptions, KdlNodeEntry, ParseError,
+    ParseErrorCause, Parser, ParserOptions, Ranged, ReaderParser, StreamParser, Writer, format,
+};
 
 enum Mode {
     Emit,
     Compare,
     Check,
+    Stream,
+    Reader,
+    Async,
+    Errors,
+    Write,
+    Format,
 }
 
 struct Args {
@@ -28,6 +48,12 @@ impl Args {
                         "emit" => Mode::Emit,
                         "check" => Mode::Check,
                         "compare" => Mode::Compare,
+                        "stream" => Mode::Stream,
+                        "reader" => Mode::Reader,
+                        "async" => Mode::Async,
+                        "errors" => Mode::Errors,
+                        "write" => Mode::Write,
+                        "format" => Mode::Format,
                         other => return Err(format!("Unexpected mode '{other}'"))?,
                     }
                 }
@@ -63,18 +89,52 @@ where
 
 type R = Result<(), Error>;
 
+/// Fixtures are parsed with every optional event enabled, so they can be checked too.
+const OPTIONS: ParserOptions = ParserOptions {
+    comments: true,
+    slashdash: true,
+    recover: true,
+};
+
 fn emit(filename: &Path) -> R {
     let contents = std::fs::read_to_string(filename)?;
-    let mut parser = pulldown_kdl::Parser::new(&contents);
+    let mut parser = Parser::with_options(&contents, OPTIONS);
     let mut events = vec![];
     while let Some(item) = parser.next_event()? {
         events.push(item);
     }
     let ron = serde_json::to_string_pretty(&events)?;
     std::fs::write(filename.with_extension("json"), ron)?;
+    let errors = describe_errors(parser.errors());
+    if !errors.is_empty() {
+        let json = serde_json::to_string_pretty(&errors)?;
+        std::fs::write(filename.with_extension("errors.json"), json)?;
+    }
     Ok(())
 }
 
+/// What is reported about each error, which is compared as JSON since errors can't be read back.
+fn describe_errors(errors: &[ParseError]) -> Vec<serde_json::Value> {
+    errors
+        .iter()
+        .map(|error| {
+            let span = error.span();
+            serde_json::json!({
+                "code": error.cause.code(),
+                "message": error.to_string(),
+                "span": { "start": span.start, "end": span.end },
+                "cause": error.cause,
+            })
+        })
+        .collect()
+}
+
+/// Annotations are ranged like events, so their ranges only match in the original document.
+/// Forgets them, so the annotations can be compared to reparsed ones.
+fn forget_ty_range<T>(ty: &mut Option<Ranged<T>>) -> Option<Range<usize>> {
+    ty.as_mut().map(|(_, range)| std::mem::take(range))
+}
+
 fn check(filename: &Path) -> R {
     let contents = std::fs::read_to_string(filename)?;
     let expected: Vec<Ranged<Event<'static>>> = serde_json::from_reader(BufReader::new(
@@ -99,6 +159,15 @@ fn check(filename: &Path) -> R {
                 }
             }};
         }
+        // Checks that an annotation range from `forget_ty_range` is delimited by parentheses.
+        macro_rules! assert_annotation {
+            ($ty_range:expr) => {{
+                if let Some(ty_range) = $ty_range {
+                    let found = &contents[ty_range];
+                    assert_expected!(found.starts_with('(') && found.ends_with(')'), true);
+                }
+            }};
+        }
         match e {
             Event::StartDocument => {
                 if depth == 0 {
@@ -119,19 +188,53 @@ fn check(filename: &Path) -> R {
             Event::NodeEnd { inline: true } => assert_expected!(found, ";"),
             Event::NodeEnd { inline: false } => assert_expected!(found, ""),
             Event::Indentation(_) => (), //nothing can be done
-            Event::NodeName(name) => assert_expected!(found, name.string),
-            Event::NodeEntry(entry) => match entry {
-                KdlNodeEntry::Argument(val) => match val {
-                    KdlValue::String(val) => assert_expected!(found, val.string),
-                    _ => todo!(),
-                },
-                KdlNodeEntry::Property { key, value } => match value {
-                    KdlValue::String(value) => {
-                        assert_expected!(found, format!("{}={}", key.string, value.string))
-                    }
-                    _ => todo!(),
-                },
-            },
+            Event::Error => (),          // the skipped text may be anything
+            Event::Comment {
+                text,
+                kind: CommentKind::Line,
+            } => assert_expected!(found, format!("//{text}")),
+            Event::Comment {
+                text,
+                kind: CommentKind::Block,
+            } => assert_expected!(found, format!("/*{text}*/")),
+            Event::SlashdashStart => assert_expected!(found, "/-"),
+            Event::SlashdashEnd => assert_expected!(found, ""),
+            Event::NodeName { mut ty, name } => {
+                assert_annotation!(forget_ty_range(&mut ty));
+                // Names may be quoted or escaped, so they're parsed again just like entries.
+                let reparsed =
+                    Parser::new(found)
+                        .map_while(Result::ok)
+                        .find_map(|(e, _)| match e {
+                            Event::NodeName { mut ty, name } => {
+                                forget_ty_range(&mut ty);
+                                Some((ty, name))
+                            }
+                            _ => None,
+                        });
+                assert_expected!(reparsed, Some((ty, name)))
+            }
+            Event::NodeEntry(mut entry) => {
+                let (KdlNodeEntry::Argument { ty, .. } | KdlNodeEntry::Property { ty, .. }) =
+                    &mut entry;
+                assert_annotation!(forget_ty_range(ty));
+                // Values may have many textual representations, so the entry is parsed again
+                // from its range and compared to the expected one.
+                let source = format!("node {found}");
+                let reparsed =
+                    Parser::new(&source)
+                        .map_while(Result::ok)
+                        .find_map(|(e, _)| match e {
+                            Event::NodeEntry(mut entry) => {
+                                let (KdlNodeEntry::Argument { ty, .. }
+                                | KdlNodeEntry::Property { ty, .. }) = &mut entry;
+                                forget_ty_range(ty);
+                                Some(entry)
+                            }
+                            _ => None,
+                        });
+                assert_expected!(reparsed, Some(entry))
+            }
         }
     }
     if depth > 1 {
@@ -146,11 +249,11 @@ fn check(filename: &Path) -> R {
 }
 
 fn compare(filename: &Path) -> R {
-    let contents = std::fs::read_to_string(filename)?;
+    let contents = std::fs::read(filename)?;
     let expected: Vec<Ranged<Event<'static>>> = serde_json::from_reader(BufReader::new(
         std::fs::File::open(filename.with_extension("json"))?,
     ))?;
-    let parser = pulldown_kdl::Parser::new(&contents);
+    let parser = Parser::from_bytes_with_options(&contents, OPTIONS);
     let generated = parser
         .map(|res| res.map_err(|e| e.into_owned()))
         .collect::<Result<Vec<_>, _>>()?;
@@ -159,6 +262,243 @@ fn compare(filename: &Path) -> R {
     Ok(())
 }
 
+/// Checks the errors recovered from against the `.errors.json` file, which documents without
+/// errors don't have.
+fn errors(filename: &Path) -> R {
+    let contents = std::fs::read(filename)?;
+    let expected: Vec<serde_json::Value> =
+        match std::fs::File::open(filename.with_extension("errors.json")) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => vec![],
+            Err(e) => Err(e)?,
+        };
+    let mut parser = Parser::from_bytes_with_options(&contents, OPTIONS);
+    for event in parser.by_ref() {
+        event.map_err(ParseError::into_owned)?;
+    }
+    let generated = describe_errors(parser.errors());
+    assert_eq!(expected, generated);
+
+    Ok(())
+}
+
+/// The events of a document as a [`Writer`] writes them, which are the same for the written
+/// document. The writer chooses its own layout, so indentation and whether nodes end inline are
+/// left out, along with the ranges of annotations. The text of errors is lost, and so are the
+/// slashdashes commenting out nothing but such text.
+fn written_events(events: Vec<Ranged<Event>>) -> Vec<Event> {
+    let mut written = vec![];
+    for (mut event, _) in events {
+        match &mut event {
+            Event::Indentation(_) | Event::Error => continue,
+            Event::SlashdashEnd if written.last() == Some(&Event::SlashdashStart) => {
+                written.pop();
+                continue;
+            }
+            Event::NodeEnd { inline } => *inline = false,
+            Event::NodeName { ty, .. }
+            | Event::NodeEntry(
+                KdlNodeEntry::Argument { ty, .. } | KdlNodeEntry::Property { ty, .. },
+            ) => {
+                forget_ty_range(ty);
+            }
+            _ => (),
+        }
+        written.push(event);
+    }
+    written
+}
+
+/// Writes the document with a [`Writer`] and parses it back, checking that nothing was lost.
+fn write(filename: &Path) -> R {
+    let contents = std::fs::read_to_string(filename)?;
+    let events = Parser::with_options(&contents, OPTIONS)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ParseError::into_owned)?;
+    let mut writer = Writer::new(String::new());
+    writer.write_events(events.iter().map(|(event, _)| event.clone()))?;
+    let written = writer.finish()?;
+    // Errors were left out, so the written document must be valid.
+    let options = ParserOptions {
+        recover: false,
+        ..OPTIONS
+    };
+    let reparsed = Parser::with_options(&written, options)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ParseError::into_owned)?;
+    assert_eq!(
+        written_events(events),
+        written_events(reparsed),
+        "Written as:\n{written}"
+    );
+
+    Ok(())
+}
+
+/// Formats the document twice, checking that the second time changes nothing and that the
+/// formatted document has the same events, comments included. Documents with errors must fail to
+/// format at their first error instead.
+fn format_document(filename: &Path) -> R {
+    let contents = std::fs::read_to_string(filename)?;
+    let options = FormatOptions::default();
+    let mut parser = Parser::with_options(&contents, OPTIONS);
+    let events = parser
+        .by_ref()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ParseError::into_owned)?;
+    if let Some(expected) = parser.errors().first() {
+        let found = match format(&contents, &options) {
+            Err(FormatError::Parse(error)) => error,
+            result => {
+                return Err(Error::Message(format!(
+                    "Expected {expected}, got {result:?}"
+                )));
+            }
+        };
+        assert_eq!(
+            (&expected.cause, expected.at),
+            (&found.cause, found.at),
+            "Wrong error"
+        );
+        return Ok(());
+    }
+
+    let formatted = format(&contents, &options)?;
+    assert_eq!(formatted, format(&formatted, &options)?, "Not idempotent");
+    let reparsed = Parser::with_options(&formatted, OPTIONS)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ParseError::into_owned)?;
+    assert_eq!(
+        written_events(events),
+        written_events(reparsed),
+        "Formatted as:\n{formatted}"
+    );
+
+    Ok(())
+}
+
+/// Chunk sizes small enough to split every token, along with a more realistic one.
+const CHUNK_SIZES: [usize; 4] = [1, 2, 7, 64];
+
+fn stream(filename: &Path) -> R {
+    let contents = std::fs::read(filename)?;
+    let expected: Vec<Ranged<Event<'static>>> = serde_json::from_reader(BufReader::new(
+        std::fs::File::open(filename.with_extension("json"))?,
+    ))?;
+    for size in CHUNK_SIZES {
+        let mut parser = StreamParser::with_options(OPTIONS);
+        let mut chunks = contents.chunks(size);
+        let mut generated = vec![];
+        let mut finished = false;
+        loop {
+            match parser.next_event() {
+                Ok(Some(item)) => generated.push(item),
+                Ok(None) => break,
+                Err(e) if e.cause == ParseErrorCause::NeedsMoreData && !finished => {
+                    match chunks.next() {
+                        Some(chunk) => parser.feed(chunk),
+                        None => {
+                            parser.finish();
+                            finished = true;
+                        }
+                    }
+                }
+                Err(e) => Err(e)?,
+            }
+        }
+        assert_eq!(expected, generated, "Chunks of {size} bytes");
+    }
+
+    Ok(())
+}
+
+fn reader(filename: &Path) -> R {
+    let contents = std::fs::read(filename)?;
+    let expected: Vec<Ranged<Event<'static>>> = serde_json::from_reader(BufReader::new(
+        std::fs::File::open(filename.with_extension("json"))?,
+    ))?;
+    for size in CHUNK_SIZES {
+        let reader = BufReader::with_capacity(size, contents.as_slice());
+        let generated =
+            ReaderParser::with_options(reader, OPTIONS).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(expected, generated, "Buffers of {size} bytes");
+    }
+
+    Ok(())
+}
+
+/// An async reader giving out at most `size` bytes at a time, which is only ready every other time
+/// it's polled.
+struct Trickle<'a> {
+    text: &'a [u8],
+    size: usize,
+    ready: bool,
+}
+
+impl AsyncRead for Trickle<'_> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let chunk = match self.as_mut().poll_fill_buf(cx) {
+            Poll::Ready(chunk) => chunk?,
+            Poll::Pending => return Poll::Pending,
+        };
+        let len = chunk.len().min(buf.len());
+        buf[..len].copy_from_slice(&chunk[..len]);
+        self.consume(len);
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncBufRead for Trickle<'_> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<&[u8]>> {
+        let this = self.get_mut();
+        if !this.ready {
+            this.ready = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        let len = this.size.min(this.text.len());
+        Poll::Ready(Ok(&this.text[..len]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        this.text = &this.text[amt..];
+        this.ready = false;
+    }
+}
+
+fn async_reader(filename: &Path) -> R {
+    let contents = std::fs::read(filename)?;
+    let expected: Vec<Ranged<Event<'static>>> = serde_json::from_reader(BufReader::new(
+        std::fs::File::open(filename.with_extension("json"))?,
+    ))?;
+    for size in CHUNK_SIZES {
+        let reader = Trickle {
+            text: &contents,
+            size,
+            ready: false,
+        };
+        let mut parser = AsyncReaderParser::with_options(reader, OPTIONS);
+        // The reader wakes the task as soon as it's pending, so it's just polled again.
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut generated = vec![];
+        loop {
+            match Pin::new(&mut parser).poll_next(&mut cx) {
+                Poll::Ready(Some(item)) => generated.push(item?),
+                Poll::Ready(None) => break,
+                Poll::Pending => (),
+            }
+        }
+        assert_eq!(expected, generated, "Reads of {size} bytes");
+    }
+
+    Ok(())
+}
+
 fn main() -> miette::Result<()> {
     let args = Args::cli().into_diagnostic()?;
     let filename = Path::new(&args.input_file);
@@ -167,6 +507,12 @@ fn main() -> miette::Result<()> {
         Mode::Emit => emit(filename),
         Mode::Check => check(filename),
         Mode::Compare => compare(filename),
+        Mode::Stream => stream(filename),
+        Mode::Reader => reader(filename),
+        Mode::Async => async_reader(filename),
+        Mode::Errors => errors(filename),
+        Mode::Write => write(filename),
+        Mode::Format => format_document(filename),
     };
     match result {
         Ok(()) => (),
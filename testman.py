@@ -17,6 +17,7 @@ if not tester.exists():
     exit(-1)
 
 tests_folder = Path('tests/')
+failing_tests_folder = Path('failing_tests/')
 
 
 type Run = tuple[Path, str]
@@ -104,7 +105,23 @@ def main():
         '-m',
         dest='mode',
         default='compare',
-        choices=['compare', 'check', 'emit', 'extract'],
+        choices=[
+            'compare',
+            'check',
+            'emit',
+            'stream',
+            'reader',
+            'async',
+            'errors',
+            'write',
+            'format',
+            'extract',
+        ],
+    )
+    _ = parser.add_argument(
+        '--failing',
+        action='store_true',
+        help='run on the documents with errors in `failing_tests/`',
     )
     args = parser.parse_args()
     mode = cast(str, args.mode)
@@ -113,7 +130,8 @@ def main():
         return 0
 
     pattern = cast(str, args.pattern) + '*.kdl'
-    failed, passed = test_all_files(tests_folder, pattern, mode)
+    folder = failing_tests_folder if args.failing else tests_folder
+    failed, passed = test_all_files(folder, pattern, mode)
     total = len(passed) + len(failed)
     if failed:
         print('fails:')